@@ -0,0 +1,265 @@
+#[cfg(feature = "egui")]
+use std::fmt::Display;
+
+#[cfg(feature = "egui")]
+use eframe::{
+    egui::{vec2, Align2, ComboBox, Rect, Sense, TextStyle, Ui},
+    epaint::Color32,
+};
+
+#[cfg(feature = "egui")]
+use crate::{Label, SubLabel, TopLabel};
+
+/// the size of a cell of the heat map
+#[cfg(feature = "egui")]
+const CELL_SIZE: f32 = 14.0;
+/// the width of the names of the labels left of the heat map
+#[cfg(feature = "egui")]
+const NAME_WIDTH: f32 = 150.0;
+/// the heat map just shows the most frequent labels, it has a cell for every pair of them
+#[cfg(feature = "egui")]
+const MAX_SHOWN_LABELS: usize = 50;
+
+/// how two labels are compared in a [`CoOccurrence`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoOccurrenceScore {
+    /// the number of data which has both labels
+    Count,
+    /// how much more often the labels appear together than if they were independent,
+    /// 1 if they are independent and 0 if they never appear together
+    Lift,
+    /// the share of the data with any of the labels which has both labels
+    Jaccard,
+}
+
+impl CoOccurrenceScore {
+    #[cfg(feature = "egui")]
+    const ALL: [CoOccurrenceScore; 3] = [
+        CoOccurrenceScore::Count,
+        CoOccurrenceScore::Lift,
+        CoOccurrenceScore::Jaccard,
+    ];
+}
+
+#[cfg(feature = "egui")]
+impl Display for CoOccurrenceScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoOccurrenceScore::Count => write!(f, "count"),
+            CoOccurrenceScore::Lift => write!(f, "lift"),
+            CoOccurrenceScore::Jaccard => write!(f, "jaccard"),
+        }
+    }
+}
+
+/// which labels appear together in the data which passes the filters, see [`FilterEngine::co_occurrence`](crate::FilterEngine::co_occurrence)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoOccurrence<L> {
+    /// the number of data which passes the filters
+    pub passing: u32,
+    /// the labels of the rows and columns of the matrix together with the number of data which has them
+    /// just labels which some data has are in the matrix
+    pub labels: Vec<(L, u32)>,
+    /// the number of data which has both labels for every row and column
+    counts: Vec<u32>,
+}
+
+impl<L> CoOccurrence<L> {
+    /// counts are the rows of the matrix one after another
+    pub(crate) fn new(passing: u32, labels: Vec<(L, u32)>, counts: Vec<u32>) -> Self {
+        assert_eq!(counts.len(), labels.len() * labels.len());
+        Self {
+            passing,
+            labels,
+            counts,
+        }
+    }
+
+    /// the number of rows and columns
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// the number of data which has the labels of row i and column j
+    pub fn count(&self, i: usize, j: usize) -> u32 {
+        self.counts[i * self.len() + j]
+    }
+
+    /// compares the labels of row i and column j
+    pub fn score(&self, i: usize, j: usize, score: CoOccurrenceScore) -> f32 {
+        let both = self.count(i, j) as f32;
+        let (a, b) = (self.labels[i].1 as f32, self.labels[j].1 as f32);
+        match score {
+            CoOccurrenceScore::Count => both,
+            //every label of the matrix is in some data so a and b are not 0
+            CoOccurrenceScore::Lift => both * self.passing as f32 / (a * b),
+            CoOccurrenceScore::Jaccard => both / (a + b - both),
+        }
+    }
+
+    /// every pair of different labels (i, j) with i < j together with its score, the highest first
+    pub fn pairs(&self, score: CoOccurrenceScore) -> Vec<(usize, usize, f32)> {
+        let mut pairs: Vec<_> = self
+            .pair_indices()
+            .map(|(i, j)| (i, j, self.score(i, j, score)))
+            .collect();
+        pairs.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+        pairs
+    }
+
+    /// the highest score of a pair of different labels, None if there is no such pair
+    pub fn max_score(&self, score: CoOccurrenceScore) -> Option<f32> {
+        self.pair_indices()
+            .map(|(i, j)| self.score(i, j, score))
+            .max_by(f32::total_cmp)
+    }
+
+    fn pair_indices(&self) -> impl Iterator<Item = (usize, usize)> {
+        let n = self.len();
+        (0..n).flat_map(move |i| (i + 1..n).map(move |j| (i, j)))
+    }
+}
+
+#[cfg(feature = "egui")]
+impl<L> CoOccurrence<L> {
+    /// shows the matrix as heat map, the labels are named on the left and in the tooltips
+    /// just the [`MAX_SHOWN_LABELS`] most frequent labels are shown
+    /// returns the row and the column of the cell which was clicked
+    pub(crate) fn show<TL, SL>(
+        &self,
+        ui: &mut Ui,
+        score: CoOccurrenceScore,
+    ) -> Option<(usize, usize)>
+    where
+        L: Label<TL, SL>,
+        TL: TopLabel,
+        SL: SubLabel,
+    {
+        if self.is_empty() {
+            ui.label("no data passes the filters");
+            return None;
+        }
+
+        //the indices of the shown labels in the order of the matrix
+        let mut shown: Vec<usize> = (0..self.len()).collect();
+        if shown.len() > MAX_SHOWN_LABELS {
+            shown.sort_by_key(|&i| std::cmp::Reverse(self.labels[i].1));
+            shown.truncate(MAX_SHOWN_LABELS);
+            shown.sort_unstable();
+            ui.label(format!(
+                "the {} most frequent of {} labels",
+                MAX_SHOWN_LABELS,
+                self.len()
+            ));
+        }
+
+        let n = shown.len();
+        let size = vec2(NAME_WIDTH + n as f32 * CELL_SIZE, n as f32 * CELL_SIZE);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        let font_id = TextStyle::Small.resolve(ui.style());
+        let text_color = ui.visuals().text_color();
+
+        //the highest score of different labels is the hottest color
+        let max = shown
+            .iter()
+            .flat_map(|&i| shown.iter().map(move |&j| (i, j)))
+            .filter(|(i, j)| i != j)
+            .map(|(i, j)| self.score(i, j, score))
+            .max_by(f32::total_cmp)
+            .unwrap_or(0.0)
+            .max(f32::EPSILON);
+        let cell_rect = |i: usize, j: usize| {
+            Rect::from_min_size(
+                rect.min + vec2(NAME_WIDTH + j as f32 * CELL_SIZE, i as f32 * CELL_SIZE),
+                vec2(CELL_SIZE, CELL_SIZE),
+            )
+        };
+        let hovered = response.hover_pos().and_then(|pos| {
+            let cell = (pos - rect.min - vec2(NAME_WIDTH, 0.0)) / CELL_SIZE;
+            (cell.x >= 0.0 && cell.y >= 0.0)
+                .then_some((cell.y as usize, cell.x as usize))
+                .filter(|&(i, j)| i < n && j < n)
+        });
+
+        for (row, &i) in shown.iter().enumerate() {
+            painter.text(
+                rect.min + vec2(NAME_WIDTH - 4.0, (row as f32 + 0.5) * CELL_SIZE),
+                Align2::RIGHT_CENTER,
+                label_name(&self.labels[i].0),
+                font_id.clone(),
+                text_color,
+            );
+            for (column, &j) in shown.iter().enumerate() {
+                let color = if i == j {
+                    Color32::from_gray(60)
+                } else {
+                    heat_color(self.score(i, j, score), score, max)
+                };
+                painter.rect_filled(cell_rect(row, column).shrink(0.5), 0.0, color);
+            }
+        }
+
+        let (row, column) = hovered?;
+        painter.rect_stroke(cell_rect(row, column), 0.0, ui.visuals().selection.stroke);
+        let (i, j) = (shown[row], shown[column]);
+        let text = format!(
+            "{} and {}\n{} together, {}: {:.2}",
+            label_name(&self.labels[i].0),
+            label_name(&self.labels[j].0),
+            self.count(i, j),
+            score,
+            self.score(i, j, score)
+        );
+        //a filter for a pair of the same label would be pointless
+        let clicked = response.clicked() && i != j;
+        response.on_hover_text(text);
+        clicked.then_some((i, j))
+    }
+}
+
+/// shows a combo box to choose the score of the heat map
+#[cfg(feature = "egui")]
+pub(crate) fn show_score_picker(ui: &mut Ui, score: &mut CoOccurrenceScore) {
+    ComboBox::from_id_source("co_occurrence_score")
+        .selected_text(score.to_string())
+        .show_ui(ui, |ui| {
+            for option in CoOccurrenceScore::ALL {
+                ui.selectable_value(score, option, option.to_string());
+            }
+        });
+}
+
+/// e.g. "Weather:Rain"
+#[cfg(feature = "egui")]
+fn label_name<L, TL, SL>(label: &L) -> String
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    match label.get_sub_level_label() {
+        Some(sub_level_label) => format!("{}:{}", label.get_top_level_label(), sub_level_label),
+        None => label.get_top_level_label().to_string(),
+    }
+}
+
+/// the lift is shown blue below 1 and red above 1, the other scores from dark to red
+#[cfg(feature = "egui")]
+fn heat_color(value: f32, score: CoOccurrenceScore, max: f32) -> Color32 {
+    let heat = match score {
+        //a lift of 4 is as hot as a lift of 1/4 is cold
+        CoOccurrenceScore::Lift => (value.max(f32::MIN_POSITIVE).ln() / 4f32.ln()).clamp(-1.0, 1.0),
+        CoOccurrenceScore::Count | CoOccurrenceScore::Jaccard => value / max,
+    };
+    let channel = |heat: f32| (40.0 + heat * 215.0) as u8;
+    if heat >= 0.0 {
+        Color32::from_rgb(channel(heat), 40, 40)
+    } else {
+        Color32::from_rgb(40, 40, channel(-heat))
+    }
+}
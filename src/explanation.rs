@@ -0,0 +1,187 @@
+#[cfg(feature = "egui")]
+use eframe::{
+    egui::{RichText, Ui},
+    epaint::Color32,
+};
+
+use crate::{
+    filter_group::{FilterGroup, FilterNode, GroupOperator},
+    query::print_filter,
+    sub_filter::SubFilter,
+    Label, LabeledData, SubLabel, TopLabel,
+};
+
+/// why an item passes or does not pass the filters, see [`FilterEngine::explain`](crate::FilterEngine::explain)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub passes: bool,
+    /// the filters which filter the item out
+    pub rejections: Vec<Rejection>,
+    /// a change of a single filter which lets the item pass, None if there is none or the item passes
+    pub fix: Option<Fix>,
+}
+
+/// a filter which filters an item out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    /// the index of the filter in the order of the filters in the [`query`](crate::FilterEngine::query)
+    pub filter_index: usize,
+    /// the filter in the syntax of [`query`](crate::query)
+    pub filter: String,
+    pub reason: RejectionReason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// the item does not have the labels the filter matches
+    MissingLabel,
+    /// the item has the labels the filter matches but the filter or its group is inverted
+    InvertedMatch,
+}
+
+/// a change of a single filter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    /// the index of the filter in the order of the filters in the [`query`](crate::FilterEngine::query)
+    pub filter_index: usize,
+    /// the filter in the syntax of [`query`](crate::query)
+    pub filter: String,
+    pub change: FilterChange,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterChange {
+    Deactivate,
+    Invert,
+}
+
+/// explains the result of the filters for data
+pub(crate) fn explain<D, L, TL, SL>(
+    filters: &FilterGroup<SubFilter<L, TL, SL>>,
+    data: &D,
+) -> Explanation
+where
+    D: LabeledData<L, TL, SL>,
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let passes = filters
+        .evaluate(&mut |filter| filter.filter(data))
+        .unwrap_or(true);
+    if passes {
+        return Explanation {
+            passes,
+            rejections: Vec::new(),
+            fix: None,
+        };
+    }
+
+    let mut rejections = Vec::new();
+    collect_rejections(filters, filters, true, data, &mut rejections);
+
+    //tries to deactivate and then to invert every filter
+    let fix = filters.filters().enumerate().find_map(|(i, changed)| {
+        [FilterChange::Deactivate, FilterChange::Invert]
+            .into_iter()
+            .find(|change| {
+                filters
+                    .evaluate(&mut |filter| {
+                        let result = filter.filter(data);
+                        if filter.id() != changed.id() {
+                            return result;
+                        }
+                        match change {
+                            FilterChange::Deactivate => None,
+                            FilterChange::Invert => result.map(|result| !result),
+                        }
+                    })
+                    .unwrap_or(true)
+            })
+            .map(|change| Fix {
+                filter_index: i,
+                filter: print_filter(changed),
+                change,
+            })
+    });
+
+    Explanation {
+        passes,
+        rejections,
+        fix,
+    }
+}
+
+/// collects the filters of a group whose result is not the expected one
+/// expected is the result the group needs so the item passes
+fn collect_rejections<D, L, TL, SL>(
+    root: &FilterGroup<SubFilter<L, TL, SL>>,
+    group: &FilterGroup<SubFilter<L, TL, SL>>,
+    expected: bool,
+    data: &D,
+    rejections: &mut Vec<Rejection>,
+) where
+    D: LabeledData<L, TL, SL>,
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    //none of the children of a not group may match
+    let expected = expected ^ (group.operator() == GroupOperator::Not);
+    for child in group.children() {
+        match child {
+            FilterNode::Filter(filter) => {
+                if filter.filter(data) == Some(!expected) {
+                    let matches = !expected ^ filter.inverted();
+                    rejections.push(Rejection {
+                        //the filters are numbered in depth first order like in the query
+                        filter_index: root
+                            .filters()
+                            .position(|f| f.id() == filter.id())
+                            .expect("filter to be in the tree"),
+                        filter: print_filter(filter),
+                        reason: if matches {
+                            RejectionReason::InvertedMatch
+                        } else {
+                            RejectionReason::MissingLabel
+                        },
+                    });
+                }
+            }
+            FilterNode::Group(group) => {
+                let result = group.evaluate(&mut |filter| filter.filter(data));
+                if result == Some(!expected) {
+                    collect_rejections(root, group, expected, data, rejections);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl Explanation {
+    pub fn show(&self, ui: &mut Ui) {
+        if self.passes {
+            ui.label("passes all filters");
+            return;
+        }
+
+        ui.label("filtered out by");
+        for rejection in &self.rejections {
+            let reason = match rejection.reason {
+                RejectionReason::MissingLabel => "the label is missing",
+                RejectionReason::InvertedMatch => "the label is excluded",
+            };
+            ui.label(
+                RichText::new(format!("{}: {}", rejection.filter, reason)).color(Color32::RED),
+            );
+        }
+        if let Some(fix) = &self.fix {
+            let change = match fix.change {
+                FilterChange::Deactivate => "deactivate",
+                FilterChange::Invert => "invert",
+            };
+            ui.label(format!("passes if you {} {}", change, fix.filter));
+        }
+    }
+}
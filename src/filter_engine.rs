@@ -0,0 +1,565 @@
+use std::{borrow::Cow, sync::Arc, thread::scope};
+
+use crate::{
+    explanation::explain,
+    filter_group::{FilterGroup, FilterNode, GroupOperator},
+    sub_filter::LabelOptions,
+    CoOccurrence, Explanation, Label, LabelDistribution, LabeledData, MainFilterState, QueryError,
+    SubFilter, SubLabel, TopLabel,
+};
+
+use self::filter_calculation::{
+    count_co_occurrence, evaluate_items, filter_items, FilterCache, LabelIndex, MainOptions,
+    ThreadCommunicator, DEFAULT_NUMBER_OF_THREADS,
+};
+pub use self::filter_calculation::{BitSet, WorkerPool};
+
+mod filter_calculation;
+
+/// the configuration of a [`FilterEngine`]
+#[derive(Clone)]
+pub struct FilterConfig {
+    /// the number of background threads which calculate the label options
+    /// with 0 threads the calculation runs in the thread which changes the filters,
+    /// e.g. where threads can not be spawned
+    pub threads: usize,
+    /// a pool whose threads are shared with other filters, threads is ignored if it is set
+    pub pool: Option<WorkerPool>,
+    /// if the widget checks the data for changes every frame, see [`LabeledData::version`]
+    /// otherwise changes have to be announced, e.g. with [`FilterEngine::data_changed`]
+    pub detect_changes: bool,
+    /// if the threads also count the co-occurrence of the labels, see [`FilterEngine::co_occurrence`]
+    pub co_occurrence: bool,
+}
+
+impl FilterConfig {
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn with_pool(mut self, pool: &WorkerPool) -> Self {
+        self.pool = Some(pool.clone());
+        self
+    }
+
+    pub fn with_change_detection(mut self, detect_changes: bool) -> Self {
+        self.detect_changes = detect_changes;
+        self
+    }
+
+    pub fn with_co_occurrence(mut self, co_occurrence: bool) -> Self {
+        self.co_occurrence = co_occurrence;
+        self
+    }
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            threads: DEFAULT_NUMBER_OF_THREADS,
+            pool: None,
+            detect_changes: false,
+            co_occurrence: false,
+        }
+    }
+}
+
+/// how much data one filter removes, see [`FilterEngine::funnel`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunnelStep {
+    /// the filter in the syntax of [`query`](crate::query)
+    pub filter: String,
+    /// the number of data which passes the other filters, as if the filter was not active
+    pub passing_other_filters: u32,
+    /// the number of data which passes all filters
+    pub passing_all_filters: u32,
+    /// the number of data which is filtered out by the filter,
+    /// negative if the filter lets more data pass, e.g. in an or group
+    pub removed: i64,
+}
+
+/// owns the filters and calculates which data passes them and which label options are usefull
+///
+/// the label options are calculated by background threads, so the engine can be used without a gui,
+/// e.g. in batch jobs or on a server
+pub struct FilterEngine<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) filters: FilterGroup<SubFilter<L, TL, SL>>,
+    pub(crate) top_level_label_options: Vec<(L, u32)>,
+    label_distribution: LabelDistribution<L>,
+    thread_communicator: Arc<ThreadCommunicator<L, TL, SL>>,
+    /// the index of the data of the last calculation, it is reused as long as the same data is passed
+    index: Option<Arc<LabelIndex<L, TL, SL>>>,
+    /// the address and the length of the data of the index
+    data_identity: (usize, usize),
+    /// the results of the last calculation, so just what changed is calculated again
+    cache: FilterCache<L, TL, SL>,
+    /// if the co-occurrence is counted with the label options
+    co_occurrence_enabled: bool,
+    co_occurrence: Option<CoOccurrence<L>>,
+    funnel: Vec<FunnelStep>,
+    /// keeps the threads alive as long as the engine exists
+    pool: Option<WorkerPool>,
+}
+
+impl<L, TL, SL> FilterEngine<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub fn new() -> FilterEngine<L, TL, SL> {
+        Self::with_config(FilterConfig::default())
+    }
+
+    pub fn with_config(config: FilterConfig) -> FilterEngine<L, TL, SL> {
+        let pool = match config.pool {
+            Some(pool) => Some(pool),
+            None if config.threads > 0 => Some(WorkerPool::new(config.threads)),
+            None => None,
+        };
+        FilterEngine {
+            filters: FilterGroup::new(0, GroupOperator::And),
+            top_level_label_options: Vec::new(),
+            label_distribution: LabelDistribution::default(),
+            thread_communicator: ThreadCommunicator::new(pool.as_ref()),
+            index: None,
+            data_identity: (0, 0),
+            cache: FilterCache::new(),
+            co_occurrence_enabled: config.co_occurrence,
+            co_occurrence: None,
+            funnel: Vec::new(),
+            pool,
+        }
+    }
+
+    /// restores the filters of state and starts the calculation of the label options for data
+    pub fn from_state<D: LabeledData<L, TL, SL>>(
+        state: &MainFilterState<L, TL, SL>,
+        data: &[D],
+    ) -> FilterEngine<L, TL, SL> {
+        Self::from_state_with_config(state, FilterConfig::default(), data)
+    }
+
+    /// like [`from_state`](Self::from_state) but the engine is configured like with [`with_config`](Self::with_config)
+    pub fn from_state_with_config<D: LabeledData<L, TL, SL>>(
+        state: &MainFilterState<L, TL, SL>,
+        config: FilterConfig,
+        data: &[D],
+    ) -> FilterEngine<L, TL, SL> {
+        let mut engine = Self::with_config(config);
+        engine.set_state(state, data);
+        engine
+    }
+
+    /// replaces all filters by the filters of state and starts the calculation of the label options
+    pub fn set_state<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        state: &MainFilterState<L, TL, SL>,
+        data: &[D],
+    ) {
+        self.filters = state
+            .filters_with_unique_ids()
+            .map(&mut |core| SubFilter::from(core.clone()));
+        self.calculate(data);
+    }
+
+    /// returns the configuration of all filters e.g. to store it
+    pub fn state(&self) -> MainFilterState<L, TL, SL> {
+        MainFilterState {
+            filters: self.filters.map(&mut |filter| filter.clone_core()),
+        }
+    }
+
+    /// replaces all filters by the filters of the query
+    /// see [`query`](crate::query) for the syntax, the names are resolved to the labels of data
+    pub fn set_query<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        query: &str,
+        data: &[D],
+    ) -> Result<(), QueryError> {
+        let index = self.index(data);
+        let state = MainFilterState::from_query(query, index.labels())?;
+        self.set_state(&state, data);
+        Ok(())
+    }
+
+    /// returns the filters in the syntax of [`query`](crate::query)
+    pub fn query(&self) -> String {
+        self.state().to_query()
+    }
+
+    /// adds a filter for the label to the top level group
+    pub fn add_filter<D: LabeledData<L, TL, SL>>(&mut self, label: L, data: &[D]) {
+        let id = self.free_id();
+        self.filters
+            .push(FilterNode::Filter(SubFilter::new(label, id)));
+        self.calculate(data);
+    }
+
+    /// moves the filter with the index from, so it has the index to afterwards
+    /// the indices are in the order of the filters in the [`query`](Self::query)
+    ///
+    /// both filters have to be in the same group, so just the order changes and not which data passes,
+    /// in the ui a filter can also be moved into another group
+    /// returns if the filter was moved
+    pub fn move_filter<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        from: usize,
+        to: usize,
+        data: &[D],
+    ) -> bool {
+        let ids: Vec<u32> = self.filters.filters().map(|filter| filter.id()).collect();
+        let (filter, target) = match (ids.get(from), ids.get(to)) {
+            (Some(&filter), Some(&target)) => (filter, target),
+            _ => return false,
+        };
+        if self.filters.group_of(&mut |f| f.id() == filter)
+            != self.filters.group_of(&mut |f| f.id() == target)
+        {
+            return false;
+        }
+
+        //the filter takes the place of the target, which moves one step towards the old place of the filter
+        let moved = self.move_filter_next_to(filter, target, from < to);
+        if moved {
+            self.calculate(data);
+        }
+        moved
+    }
+
+    /// moves the filter in front of or after the target filter
+    /// returns if the filter was moved
+    pub(crate) fn move_filter_next_to(&mut self, filter: u32, target: u32, after: bool) -> bool {
+        if filter == target || !self.filters.filters().any(|f| f.id() == target) {
+            return false;
+        }
+        match self.filters.take_filter(&mut |f| f.id() == filter) {
+            Some(filter) => {
+                self.filters
+                    .insert_filter(&mut |f| f.id() == target, filter, after)
+                    .unwrap_or_else(|_| unreachable!("the target to be in the tree"));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// returns if data passes the filters
+    pub fn matches<D: LabeledData<L, TL, SL>>(&self, data: &D) -> bool {
+        self.filters
+            .evaluate(&mut |filter| filter.filter(data))
+            .unwrap_or(true)
+    }
+
+    /// returns why data passes or does not pass the filters
+    pub fn explain<D: LabeledData<L, TL, SL>>(&self, data: &D) -> Explanation {
+        explain(&self.filters, data)
+    }
+
+    /// returns the indices of the data which passes the filters
+    pub fn filter_map<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> Vec<usize> {
+        self.filter_indices(data).collect()
+    }
+
+    /// returns the data which passes the filters as a set of indices
+    /// the data is scanned, see [`last_filter_bit_set`](Self::last_filter_bit_set) for the result of the calculation
+    pub fn filter_bit_set<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> BitSet {
+        let mut items = BitSet::empty(data.len());
+        data.iter()
+            .enumerate()
+            .filter(|(_i, data)| self.matches(*data))
+            .for_each(|(i, _data)| items.insert(i));
+        items
+    }
+
+    /// like [`filter_bit_set`](Self::filter_bit_set) but for the data last passed to
+    /// [`calculate`](Self::calculate), [`set_data`](Self::set_data) or [`data_appended`](Self::data_appended)
+    /// without scanning it, the result of the calculation is borrowed if it is finished
+    ///
+    /// it is outdated if the data was changed since then, returns None if there was no calculation
+    pub fn last_filter_bit_set(&self) -> Option<Cow<'_, BitSet>> {
+        let index = self.index.as_ref()?;
+        Some(match self.cache.result(index) {
+            Some(result) => Cow::Borrowed(result),
+            None => Cow::Owned(
+                evaluate_items(&self.filters, &mut |filter| filter_items(filter, index))
+                    .unwrap_or_else(|| BitSet::full(index.len())),
+            ),
+        })
+    }
+
+    /// iterates lazily over the indices of the data which passes the filters
+    pub fn filter_indices<'a, D: LabeledData<L, TL, SL>>(
+        &'a self,
+        data: &'a [D],
+    ) -> impl Iterator<Item = usize> + 'a {
+        data.iter()
+            .enumerate()
+            .filter_map(|(i, data)| self.matches(data).then_some(i))
+    }
+
+    /// iterates lazily over the data which passes the filters
+    pub fn filter_data<'a, D: LabeledData<L, TL, SL>>(
+        &'a self,
+        data: &'a [D],
+    ) -> impl Iterator<Item = &'a D> + 'a {
+        self.filter_indices(data).map(|i| &data[i])
+    }
+
+    /// returns the number of data which passes the filters
+    pub fn filter_count<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> usize {
+        self.filter_indices(data).count()
+    }
+
+    /// like [`filter_map`](Self::filter_map) but the data is split between as many threads as the engine has
+    pub fn par_filter_map<D: LabeledData<L, TL, SL> + Sync>(&self, data: &[D]) -> Vec<usize> {
+        let threads = self.pool.as_ref().map(WorkerPool::threads).unwrap_or(1);
+        let chunk_len = data.len().div_ceil(threads).max(1);
+        scope(|scope| {
+            let chunks: Vec<_> = data
+                .chunks(chunk_len)
+                .enumerate()
+                .map(|(chunk, data)| {
+                    scope.spawn(move || {
+                        data.iter()
+                            .enumerate()
+                            .filter_map(|(i, data)| {
+                                self.matches(data).then_some(chunk * chunk_len + i)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            chunks
+                .into_iter()
+                .flat_map(|chunk| chunk.join().expect("the filters not to panic"))
+                .collect()
+        })
+    }
+
+    /// the top level labels which can be used for a new filter, the usefull ones last
+    /// together with the number of data which is left if a filter for them is added
+    pub fn top_level_label_options(&self) -> &[(L, u32)] {
+        &self.top_level_label_options
+    }
+
+    /// how the data which passes the filters spreads over the labels, e.g. to show a histogram
+    /// the numbers are those of the last finished calculation
+    pub fn label_distribution(&self) -> &LabelDistribution<L> {
+        &self.label_distribution
+    }
+
+    /// for every pair of labels how much data which passes the filters has both,
+    /// e.g. to find labels which tend to appear together
+    /// it is counted by the threads if it is enabled with [`FilterConfig::with_co_occurrence`]
+    /// or [`set_co_occurrence`](Self::set_co_occurrence), the numbers are those of the last finished calculation
+    /// returns None if it is not enabled or no calculation with it is finished
+    pub fn co_occurrence(&self) -> Option<&CoOccurrence<L>> {
+        self.co_occurrence.as_ref()
+    }
+
+    /// enables or disables the counting of the [`co_occurrence`](Self::co_occurrence)
+    /// the calculation is started again if it is enabled
+    pub fn set_co_occurrence<D: LabeledData<L, TL, SL>>(&mut self, enabled: bool, data: &[D]) {
+        if self.co_occurrence_enabled == enabled {
+            return;
+        }
+        self.co_occurrence_enabled = enabled;
+        if enabled {
+            self.calculate(data);
+        } else {
+            self.co_occurrence = None;
+        }
+    }
+
+    /// like [`co_occurrence`](Self::co_occurrence) but counted in the calling thread for the current filters,
+    /// e.g. in batch jobs
+    ///
+    /// the effort grows with the square of the number of labels, so it should not be called every frame
+    pub fn count_co_occurrence<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> CoOccurrence<L> {
+        let passing = self.filter_bit_set(data);
+        count_co_occurrence(&LabelIndex::from(data), &passing, || false)
+            .expect("the counting not to be outdated")
+    }
+
+    /// the label options of every filter in the order of the filters in the [`query`](Self::query)
+    pub fn filter_label_options(&self) -> impl Iterator<Item = &LabelOptions<L>> {
+        self.filters.filters().map(|filter| filter.label_options())
+    }
+
+    /// returns for every filter in the order of the [`query`](Self::query) how much data it removes
+    /// from the result of all filters, e.g. to find the filter which is responsible for an empty result
+    /// it is counted by the threads, the numbers are those of the last finished calculation
+    pub fn funnel(&self) -> &[FunnelStep] {
+        &self.funnel
+    }
+
+    /// starts the calculation of the label options for data in the background
+    /// a running calculation is discarded
+    ///
+    /// just the filters whose options depend on a change since the last calculation are calculated again
+    pub fn calculate<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        let index = self.index(data);
+        let delta = self
+            .cache
+            .delta(index, &self.filters, self.co_occurrence_enabled);
+        self.thread_communicator.start(delta);
+    }
+
+    /// starts the calculation again if data is not the data of the last calculation
+    /// data is compared by its address, its length and the [`LabeledData::version`] of every item
+    /// returns if the calculation was started
+    pub fn set_data<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) -> bool {
+        let up_to_date = match &self.index {
+            Some(index) => self.data_identity == data_identity(data) && index.is_index_of(data),
+            None => false,
+        };
+        if up_to_date {
+            return false;
+        }
+        //the data may have been changed in place
+        self.index = None;
+        self.calculate(data);
+        true
+    }
+
+    /// starts the calculation again after the labels of data were changed in place,
+    /// which can not be noticed if the data does not implement [`LabeledData::version`]
+    pub fn data_changed<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        self.index = None;
+        self.calculate(data);
+    }
+
+    /// starts the calculation again after items were appended to the data of the last calculation
+    /// the index of the data is extended instead of being built again
+    pub fn data_appended<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        match self.index.as_mut() {
+            Some(index) if index.is_prefix_of(data) => {
+                //the cache holds the old index and its results are outdated
+                self.cache = FilterCache::new();
+                Arc::make_mut(index).append(data);
+                self.data_identity = data_identity(data);
+            }
+            _ => self.index = None,
+        }
+        self.calculate(data);
+    }
+
+    /// returns if the index was built from data
+    /// just the identity of data is compared, its versions are only checked by [`set_data`](Self::set_data)
+    fn is_index_of<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> bool {
+        self.index.is_some() && self.data_identity == data_identity(data)
+    }
+
+    /// returns the index of data, it is just built if other data was passed last
+    fn index<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) -> Arc<LabelIndex<L, TL, SL>> {
+        match &self.index {
+            Some(index) if self.is_index_of(data) => index.clone(),
+            _ => {
+                let index = Arc::new(LabelIndex::from(data));
+                self.index = Some(index.clone());
+                self.data_identity = data_identity(data);
+                index
+            }
+        }
+    }
+
+    /// takes the label options if the calculation is finished
+    /// returns if the label options were updated
+    pub fn try_update(&mut self) -> bool {
+        match self.thread_communicator.try_get_finished() {
+            Some((filters, main_options)) => {
+                self.set_label_options(filters, main_options);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// blocks until the calculation is finished and takes the label options
+    /// returns if the label options were updated
+    pub fn wait(&mut self) -> bool {
+        match self.thread_communicator.wait_finished() {
+            Some((filters, main_options)) => {
+                self.set_label_options(filters, main_options);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn set_label_options(
+        &mut self,
+        filters: FilterGroup<SubFilter<L, TL, SL>>,
+        main_options: MainOptions<L>,
+    ) {
+        self.cache.store(&filters, &main_options);
+        self.filters = filters;
+        self.top_level_label_options = main_options.label_options;
+        self.label_distribution = main_options.distribution;
+        //the calculation may have been started before it was disabled
+        self.co_occurrence = main_options
+            .co_occurrence
+            .filter(|_| self.co_occurrence_enabled);
+        self.funnel = main_options.funnel;
+    }
+
+    /// sets a callback which is called from the background threads whenever the progress changed
+    /// and when the calculation is finished, e.g. to repaint the ui
+    pub fn set_repaint_callback(&self, callback: impl Fn() + Send + Sync + 'static) {
+        self.thread_communicator
+            .set_repaint_callback(Box::new(callback));
+    }
+
+    /// returns a progress (a, b) of the calculation which means a/b
+    pub fn progress(&self) -> (usize, usize) {
+        self.thread_communicator.get_progress()
+    }
+
+    /// returns the smallest id which is neither used by a filter nor by a group
+    pub(crate) fn free_id(&self) -> u32 {
+        let groups = self.filters.groups();
+        (0..)
+            .find(|&id| {
+                !groups.iter().any(|&(group, _depth)| group == id)
+                    && !self.filters.filters().any(|filter| filter.id() == id)
+            })
+            .expect("not to have more than u32 many filters")
+    }
+}
+
+/// data is identified by its address and length
+/// so data which is changed in place keeps its identity, the versions of the items are just checked by
+/// [`FilterEngine::set_data`] because it calls [`LabeledData::version`] for every item
+fn data_identity<D>(data: &[D]) -> (usize, usize) {
+    (data.as_ptr() as usize, data.len())
+}
+
+impl<L, TL, SL> Drop for FilterEngine<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// discards a running calculation, the threads of the pool are stopped if no one else uses it
+    fn drop(&mut self) {
+        self.thread_communicator.shutdown();
+    }
+}
+
+impl<L, TL, SL> Default for FilterEngine<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
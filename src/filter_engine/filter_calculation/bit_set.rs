@@ -0,0 +1,141 @@
+/// a set of item indices below len, one bit per item
+///
+/// e.g. the data which passes the filters, see [`FilterEngine::filter_bit_set`](crate::FilterEngine::filter_bit_set)
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    pub(crate) fn empty(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub(crate) fn full(len: usize) -> Self {
+        let mut set = Self {
+            words: vec![u64::MAX; len.div_ceil(64)],
+            len,
+        };
+        set.clear_tail();
+        set
+    }
+
+    /// sets the length to len, the new items are not in the set
+    pub(crate) fn grow(&mut self, len: usize) {
+        self.words.resize(len.div_ceil(64), 0);
+        self.len = len;
+    }
+
+    pub(crate) fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    /// the number of items the set can contain, not the number of items in it
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// returns if no item is in the set, independent of [`len`](Self::len)
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// the number of items in the set
+    pub fn count(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub(crate) fn intersection_count(&self, other: &BitSet) -> u32 {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones())
+            .sum()
+    }
+
+    /// the number of items which are in all three sets
+    pub(crate) fn intersection_count_of_three(&self, other: &BitSet, third: &BitSet) -> u32 {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .zip(third.words.iter())
+            .map(|((a, b), c)| (a & b & c).count_ones())
+            .sum()
+    }
+
+    pub(crate) fn and(&mut self, other: &BitSet) {
+        self.zip_with(other, |a, b| a & b);
+    }
+
+    pub(crate) fn or(&mut self, other: &BitSet) {
+        self.zip_with(other, |a, b| a | b);
+    }
+
+    pub(crate) fn xor(&mut self, other: &BitSet) {
+        self.zip_with(other, |a, b| a ^ b);
+    }
+
+    pub(crate) fn and_not(&mut self, other: &BitSet) {
+        self.zip_with(other, |a, b| a & !b);
+    }
+
+    /// the items of if_true which are in condition and the items of if_false which are not
+    pub(crate) fn select(condition: &BitSet, if_true: &BitSet, if_false: &BitSet) -> BitSet {
+        debug_assert!(condition.len == if_true.len && condition.len == if_false.len);
+        Self {
+            words: condition
+                .words
+                .iter()
+                .zip(if_true.words.iter())
+                .zip(if_false.words.iter())
+                .map(|((c, t), f)| (c & t) | (!c & f))
+                .collect(),
+            len: condition.len,
+        }
+    }
+
+    pub(crate) fn invert(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = !*word);
+        self.clear_tail();
+    }
+
+    /// iterates over the items of the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+
+    fn zip_with(&mut self, other: &BitSet, f: impl Fn(u64, u64) -> u64) {
+        debug_assert_eq!(self.len, other.len);
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(a, &b)| *a = f(*a, b));
+    }
+
+    /// the bits after len have to be zero so count is correct
+    fn clear_tail(&mut self) {
+        let tail = self.len % 64;
+        if tail > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << tail) - 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,612 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    filter_group::FilterGroup,
+    sub_filter::{LabelOptions, SubFilter, SubFilterCore},
+    Label, SubLabel, TopLabel,
+};
+
+use super::{
+    bit_set::BitSet, evaluate_items, filter_items, for_each_outcome, label_index::LabelIndex,
+    work::Work, MainOptions, Outcomes,
+};
+
+/// what the label options of a filter are calculated from
+#[derive(PartialEq, Eq)]
+pub(crate) struct FilterContext {
+    /// the items whose result depends on the filter
+    /// for the main filter options these are the items which are not filtered out
+    pub(crate) relevant: BitSet,
+    /// the number of items which are not filtered out whatever the filter returns
+    pub(crate) passing: u32,
+    /// if the relevant items are not filtered out when the filter returns false, e.g. in a not group
+    pub(crate) negated: bool,
+    /// the number of items which pass the tree if the filter is not active
+    pub(crate) passing_other_filters: u32,
+    /// the number of items which pass the tree
+    pub(crate) passing_all_filters: u32,
+}
+
+/// the work of a calculation together with the results of the last calculation which are still valid
+pub(crate) struct Delta<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) all_filters: Arc<FilterGroup<SubFilterCore<L, TL, SL>>>,
+    pub(crate) work: Vec<Work<L, TL, SL>>,
+    /// the filters whose cached label options are still valid by their id
+    pub(crate) finished_filters: HashMap<u32, SubFilter<L, TL, SL>>,
+    /// None if the main filter options have to be calculated
+    pub(crate) finished_main_filter_label: Option<MainOptions<L>>,
+}
+
+type MainFilters<L, TL, SL> = Arc<FilterGroup<SubFilterCore<L, TL, SL>>>;
+
+struct CachedOptions<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    core: SubFilterCore<L, TL, SL>,
+    context: Arc<FilterContext>,
+    options: LabelOptions<L>,
+}
+
+/// remembers the items which pass every filter and what the label options were calculated from
+/// so a change of one filter just recalculates the filters whose options depend on it
+#[allow(clippy::type_complexity)]
+pub(crate) struct FilterCache<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// the index the cached items belong to
+    index: Option<Arc<LabelIndex<L, TL, SL>>>,
+    /// the items which pass a filter by the id of the filter, None if the filter is not active
+    leaves: HashMap<u32, (SubFilterCore<L, TL, SL>, Option<BitSet>)>,
+    /// the label options of the last finished calculation by the id of the filter
+    options: HashMap<u32, CachedOptions<L, TL, SL>>,
+    /// the main options together with what they were calculated from
+    main_options: Option<(Arc<FilterContext>, MainFilters<L, TL, SL>, MainOptions<L>)>,
+    /// the contexts of the calculation which was started last, they are cached with its result
+    pending: HashMap<u32, (SubFilterCore<L, TL, SL>, Arc<FilterContext>)>,
+    pending_main: Option<(Arc<FilterContext>, MainFilters<L, TL, SL>)>,
+}
+
+impl<L, TL, SL> FilterCache<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            index: None,
+            leaves: HashMap::new(),
+            options: HashMap::new(),
+            main_options: None,
+            pending: HashMap::new(),
+            pending_main: None,
+        }
+    }
+
+    /// returns the work which is needed to calculate the label options of filters for the index
+    /// and, if co_occurrence is set, the co-occurrence of the labels
+    pub(crate) fn delta(
+        &mut self,
+        index: Arc<LabelIndex<L, TL, SL>>,
+        filters: &FilterGroup<SubFilter<L, TL, SL>>,
+        co_occurrence: bool,
+    ) -> Delta<L, TL, SL> {
+        //nothing is valid for other data
+        if !matches!(&self.index, Some(cached) if Arc::ptr_eq(cached, &index)) {
+            *self = Self::new();
+            self.index = Some(index.clone());
+        }
+
+        let all_filters = Arc::new(filters.map(&mut |f| f.clone_core()));
+        self.update_leaves(&all_filters, &index);
+
+        let len = index.len();
+        let leaves = &self.leaves;
+        let mut leaf = |f: &SubFilterCore<L, TL, SL>| {
+            leaves.get(&f.id()).and_then(|(_core, items)| items.clone())
+        };
+        let passing_all_filters =
+            evaluate_items(&all_filters, &mut leaf).unwrap_or_else(|| BitSet::full(len));
+        let passing_all_filters_counter = passing_all_filters.count();
+        let mut contexts = Vec::new();
+        //the root decides which items pass
+        let root = Outcomes {
+            if_passing: BitSet::full(len),
+            if_failing: BitSet::empty(len),
+            if_inactive: BitSet::full(len),
+        };
+        for_each_outcome(&all_filters, &root, &mut leaf, &mut |core, outcomes| {
+            let Outcomes {
+                if_passing: with_filter,
+                if_failing: mut relevant,
+                if_inactive,
+            } = outcomes;
+            let passing = relevant.intersection_count(&with_filter);
+            //the items which just pass without the filter
+            let negated = relevant.count() > passing;
+            relevant.xor(&with_filter);
+            let context = Arc::new(FilterContext {
+                relevant,
+                passing,
+                negated,
+                passing_other_filters: if_inactive.count(),
+                passing_all_filters: passing_all_filters_counter,
+            });
+            contexts.push((core.clone(), context));
+        });
+
+        let mut work = Vec::new();
+        let mut finished_filters = HashMap::new();
+        self.pending.clear();
+        for (core, context) in contexts {
+            match self.options.get(&core.id()) {
+                Some(cached) if cached.core.same_config(&core) && cached.context == context => {
+                    finished_filters.insert(
+                        core.id(),
+                        SubFilter::from_core_with_label_options(
+                            core.clone(),
+                            cached.options.clone(),
+                        ),
+                    );
+                }
+                _ => work.push(Work::FilterLabel {
+                    filter: core.clone(),
+                    context: context.clone(),
+                    index: index.clone(),
+                }),
+            }
+            self.pending.insert(core.id(), (core, context));
+        }
+
+        let main_context = Arc::new(FilterContext {
+            relevant: passing_all_filters,
+            passing: 0,
+            negated: false,
+            passing_other_filters: passing_all_filters_counter,
+            passing_all_filters: passing_all_filters_counter,
+        });
+        //the funnel depends on every filter
+        let finished_main_filter_label = match &self.main_options {
+            Some((context, filters, options))
+                if *context == main_context
+                    && filters.same_config(&all_filters, &mut |a, b| a.same_config(b))
+                    && (!co_occurrence || options.co_occurrence.is_some()) =>
+            {
+                Some(options.clone())
+            }
+            _ => {
+                work.push(Work::MainFilterOptins {
+                    context: main_context.clone(),
+                    filters: all_filters.clone(),
+                    index,
+                    co_occurrence,
+                });
+                None
+            }
+        };
+        self.pending_main = Some((main_context, all_filters.clone()));
+
+        Delta {
+            all_filters,
+            work,
+            finished_filters,
+            finished_main_filter_label,
+        }
+    }
+
+    /// caches the label options of the calculation which was started last
+    pub(crate) fn store(
+        &mut self,
+        filters: &FilterGroup<SubFilter<L, TL, SL>>,
+        main_options: &MainOptions<L>,
+    ) {
+        self.options.clear();
+        for filter in filters.filters() {
+            if let Some((core, context)) = self.pending.remove(&filter.id()) {
+                let options = filter.label_options().clone();
+                self.options.insert(
+                    filter.id(),
+                    CachedOptions {
+                        core,
+                        context,
+                        options,
+                    },
+                );
+            }
+        }
+        if let Some((context, filters)) = self.pending_main.take() {
+            self.main_options = Some((context, filters, main_options.clone()));
+        }
+    }
+
+    /// the items which pass the filters of the calculation which was started last
+    /// None if the last calculation was not for the index
+    pub(crate) fn result(&self, index: &Arc<LabelIndex<L, TL, SL>>) -> Option<&BitSet> {
+        if !matches!(&self.index, Some(cached) if Arc::ptr_eq(cached, index)) {
+            return None;
+        }
+        match (&self.pending_main, &self.main_options) {
+            (Some((context, _)), _) | (None, Some((context, _, _))) => Some(&context.relevant),
+            (None, None) => None,
+        }
+    }
+
+    /// calculates the items of the filters which are new or changed and forgets removed filters
+    fn update_leaves(
+        &mut self,
+        all_filters: &FilterGroup<SubFilterCore<L, TL, SL>>,
+        index: &LabelIndex<L, TL, SL>,
+    ) {
+        self.leaves
+            .retain(|id, _| all_filters.filters().any(|f| f.id() == *id));
+        for core in all_filters.filters() {
+            let up_to_date = matches!(self.leaves.get(&core.id()), Some((cached, _)) if cached.same_config(core));
+            if !up_to_date {
+                self.leaves
+                    .insert(core.id(), (core.clone(), filter_items(core, index)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_items, for_each_outcome, BitSet, LabelIndex, Outcomes};
+    use crate::sub_filter::SubFilterCore;
+    #[cfg(feature = "egui")]
+    use crate::MainFilter;
+    use crate::{
+        FilterChange, FilterConfig, FilterEngine, FunnelStep, Label, LabelDistribution,
+        LabelOptions, MainFilterState,
+    };
+
+    #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+    struct TestLabel(&'static str, &'static str);
+
+    impl Label<&'static str, &'static str> for TestLabel {
+        fn get_top_level_label(&self) -> &&'static str {
+            &self.0
+        }
+
+        fn get_sub_level_label(&self) -> Option<&&'static str> {
+            Some(&self.1)
+        }
+    }
+
+    type Engine = FilterEngine<TestLabel, &'static str, &'static str>;
+
+    const LABELS: &[(&str, &[&str])] = &[
+        ("Weather", &["Rain", "Snow", "Sun"]),
+        ("Road", &["Highway", "City"]),
+        ("Daytime", &["Day", "Night"]),
+    ];
+
+    /// data with a few random labels
+    fn test_data(len: usize) -> Vec<Vec<TestLabel>> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+        (0..len)
+            .map(|_| {
+                let mut labels: Vec<TestLabel> = (0..random(4))
+                    .map(|_| {
+                        let (top_level_label, sub_level_labels) = LABELS[random(LABELS.len())];
+                        TestLabel(
+                            top_level_label,
+                            sub_level_labels[random(sub_level_labels.len())],
+                        )
+                    })
+                    .collect();
+                labels.sort();
+                labels.dedup();
+                labels
+            })
+            .collect()
+    }
+
+    type Results = (
+        Vec<LabelOptions<TestLabel>>,
+        Vec<(TestLabel, u32)>,
+        LabelDistribution<TestLabel>,
+        Vec<FunnelStep>,
+        usize,
+    );
+
+    fn results(engine: &Engine, data: &[&[TestLabel]]) -> Results {
+        (
+            engine.filter_label_options().cloned().collect(),
+            engine.top_level_label_options().to_vec(),
+            engine.label_distribution().clone(),
+            engine.funnel().to_vec(),
+            engine.filter_count(data),
+        )
+    }
+
+    fn fresh_results(query: &str, data: &[&[TestLabel]]) -> Results {
+        let mut engine = Engine::with_config(FilterConfig::default().with_threads(0));
+        engine.set_query(query, data).unwrap();
+        engine.wait();
+        results(&engine, data)
+    }
+
+    #[test]
+    fn cached_options_are_fresh_options() {
+        let data = test_data(500);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+
+        //every query changes the last one a bit so most of the results come from the cache
+        let queries = [
+            "Weather:Rain",
+            "Weather:Rain AND Road:City",
+            "Weather:Rain AND NOT Road:City",
+            "Weather:{Rain, Snow} AND NOT Road:City",
+            "Weather:{Rain, Snow} AND NOT Road:City AND Daytime:*",
+            "Weather:{Rain, Snow} AND NOT Road:City AND #Daytime:*",
+            "Weather:ALL{Rain, Snow} OR NOT Road:City OR Daytime:*",
+            "Weather:ALL{Rain, Snow} OR NOT (Road:City AND Daytime:Night)",
+            "Weather:Sun OR NOT (Road:City AND Daytime:Night)",
+            "Weather:Sun",
+            "",
+        ];
+        let mut engine = Engine::with_config(FilterConfig::default().with_threads(0));
+        for query in queries {
+            engine.set_query(query, &data).unwrap();
+            engine.wait();
+            assert_eq!(
+                results(&engine, &data),
+                fresh_results(query, &data),
+                "{}",
+                query
+            );
+            //the funnel is counted apart from the label options
+            let removed: Vec<i64> = engine
+                .filter_label_options()
+                .map(LabelOptions::removed)
+                .collect();
+            let funnel: Vec<i64> = engine.funnel().iter().map(|step| step.removed).collect();
+            assert_eq!(funnel, removed, "{}", query);
+        }
+
+        //moving a filter just changes the order of the options
+        engine
+            .set_query("Weather:Rain AND NOT Road:City AND Daytime:*", &data)
+            .unwrap();
+        engine.wait();
+        engine.move_filter(2, 0, &data);
+        engine.wait();
+        assert_eq!(
+            results(&engine, &data),
+            fresh_results("Daytime:* AND Weather:Rain AND NOT Road:City", &data)
+        );
+    }
+
+    #[test]
+    fn outcomes_are_those_of_a_forced_filter() {
+        let data = test_data(300);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+        let labels: Vec<TestLabel> = data
+            .iter()
+            .flat_map(|labels| labels.iter().cloned())
+            .collect();
+        let index = LabelIndex::from(data.as_slice());
+
+        let queries = [
+            "Weather:Rain",
+            "#Weather:Rain AND Road:City",
+            "Weather:Rain OR NOT (Road:City AND #Daytime:Night) OR Weather:Sun",
+            "NOT (Weather:{Rain, Snow} OR (Road:City AND NOT (Daytime:Day)))",
+            "(#Weather:Rain OR #Road:City) AND Daytime:* AND (Weather:ALL{Rain, Snow} OR Road:*)",
+        ];
+        for query in queries {
+            let filters = MainFilterState::from_query(query, &labels).unwrap().filters;
+            let root = Outcomes {
+                if_passing: BitSet::full(data.len()),
+                if_failing: BitSet::empty(data.len()),
+                if_inactive: BitSet::full(data.len()),
+            };
+            let mut filter_count = 0;
+            for_each_outcome(
+                &filters,
+                &root,
+                &mut |filter| filter_items(filter, &index),
+                &mut |filter, outcomes| {
+                    filter_count += 1;
+                    let forced = |result: Option<bool>| -> Vec<usize> {
+                        (0..data.len())
+                            .filter(|&i| {
+                                filters
+                                    .evaluate(&mut |f| match f.id() == filter.id() {
+                                        true => result,
+                                        false => f.filter(&data[i]),
+                                    })
+                                    .unwrap_or(true)
+                            })
+                            .collect()
+                    };
+                    let outcomes = [
+                        outcomes.if_passing,
+                        outcomes.if_failing,
+                        outcomes.if_inactive,
+                    ]
+                    .map(|items| items.iter().collect::<Vec<_>>());
+                    assert_eq!(
+                        outcomes,
+                        [forced(Some(true)), forced(Some(false)), forced(None)],
+                        "{}",
+                        query
+                    );
+                },
+            );
+            assert_eq!(filter_count, filters.filters().count());
+        }
+    }
+
+    #[test]
+    fn explanations_agree_with_the_filters() {
+        let data = test_data(300);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+
+        let queries = [
+            "NOT (Weather:Rain OR Road:City)",
+            "Daytime:Day AND NOT (Weather:* AND NOT (Road:Highway))",
+            "#Weather:Rain",
+            "#Weather:Rain AND Road:City",
+            "Weather:Sun OR #Road:City OR Daytime:Night",
+            "Weather:ALL{Rain, Snow}",
+            "NOT Weather:ALL{Rain, Sun} AND Road:{City, Highway}",
+        ];
+        let mut engine = Engine::with_config(FilterConfig::default().with_threads(0));
+        for query in queries {
+            engine.set_query(query, &data).unwrap();
+            let state = engine.state();
+            for item in &data {
+                let explanation = engine.explain(item);
+                assert_eq!(explanation.passes, engine.matches(item), "{}", query);
+                assert_eq!(
+                    explanation.rejections.is_empty(),
+                    explanation.passes,
+                    "{}",
+                    query
+                );
+
+                //the fix lets the item pass
+                let fix = match explanation.fix {
+                    Some(fix) => fix,
+                    None => continue,
+                };
+                let mut filter_index = 0;
+                let filters = state.filters.renumber(0, &mut 1, &mut |filter, id| {
+                    let changed = filter_index == fix.filter_index;
+                    filter_index += 1;
+                    SubFilterCore::new(
+                        id,
+                        filter.label().clone(),
+                        filter.matching().clone(),
+                        filter.inverted() != (changed && fix.change == FilterChange::Invert),
+                        filter.active() && !(changed && fix.change == FilterChange::Deactivate),
+                    )
+                });
+                let passes = filters
+                    .evaluate(&mut |filter| filter.filter(item))
+                    .unwrap_or(true);
+                assert!(passes, "{} {:?}", query, fix);
+            }
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    #[test]
+    fn explaining_an_unknown_item_returns_none() {
+        let data = test_data(10);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+        let mut main_filter: MainFilter<TestLabel, &'static str, &'static str> =
+            MainFilter::with_config(FilterConfig::default().with_threads(0));
+        main_filter.set_query("Weather:Rain", &data).unwrap();
+        assert!(main_filter.explain(&data, data.len() - 1).is_some());
+        assert!(main_filter.explain(&data, data.len()).is_none());
+        assert!(main_filter.explain(&data[..0], 0).is_none());
+    }
+
+    #[test]
+    fn moving_keeps_the_meaning() {
+        let data = test_data(300);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+        let mut engine = Engine::with_config(FilterConfig::default().with_threads(0));
+        engine
+            .set_query(
+                "Weather:Rain AND (Road:City OR Weather:Sun OR Daytime:Night)",
+                &data,
+            )
+            .unwrap();
+        engine.wait();
+        let filter_map = engine.filter_map(&data);
+
+        //out of the group and into it
+        assert!(!engine.move_filter(0, 2, &data));
+        assert!(!engine.move_filter(3, 0, &data));
+        assert_eq!(
+            engine.query(),
+            "Weather:Rain AND (Road:City OR Weather:Sun OR Daytime:Night)"
+        );
+
+        assert!(engine.move_filter(1, 3, &data));
+        assert!(engine.move_filter(3, 2, &data));
+        assert_eq!(
+            engine.query(),
+            "Weather:Rain AND (Weather:Sun OR Road:City OR Daytime:Night)"
+        );
+        engine.wait();
+        assert_eq!(engine.filter_map(&data), filter_map);
+    }
+
+    #[test]
+    fn changed_data_is_scanned() {
+        let data = test_data(300);
+        let mut data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+        let mut engine = Engine::with_config(FilterConfig::default().with_threads(0));
+        engine
+            .set_query("Weather:Rain OR Road:City", &data)
+            .unwrap();
+        engine.wait();
+        let last: Vec<usize> = engine.last_filter_bit_set().unwrap().iter().collect();
+        assert_eq!(last, engine.filter_map(&data));
+
+        //the data keeps its address and length but not its labels
+        data.reverse();
+        let expected: Vec<usize> = (0..data.len())
+            .filter(|i| engine.matches(&data[*i]))
+            .collect();
+        assert_ne!(last, expected);
+        assert_eq!(engine.filter_map(&data), expected);
+        assert_eq!(engine.filter_count(&data), expected.len());
+        assert_eq!(
+            engine.filter_bit_set(&data).iter().collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(engine.par_filter_map(&data), expected);
+    }
+
+    #[test]
+    fn duplicate_ids_are_given_anew() {
+        let data = test_data(200);
+        let data: Vec<&[TestLabel]> = data.iter().map(|labels| labels.as_slice()).collect();
+        let labels: Vec<TestLabel> = data
+            .iter()
+            .flat_map(|labels| labels.iter().cloned())
+            .collect();
+
+        //e.g. a hand edited state where every group and filter has the id 1
+        let query = "Weather:Rain AND NOT (Road:City OR Daytime:Night)";
+        let mut state = MainFilterState::from_query(query, &labels).unwrap();
+        state.filters = state.filters.renumber(1, &mut 1, &mut |filter, _id| {
+            SubFilterCore::new(
+                1,
+                filter.label().clone(),
+                filter.matching().clone(),
+                filter.inverted(),
+                filter.active(),
+            )
+        });
+
+        let mut engine =
+            Engine::from_state_with_config(&state, FilterConfig::default().with_threads(0), &data);
+        engine.wait();
+        assert_eq!(results(&engine, &data), fresh_results(query, &data));
+        assert_eq!(engine.state().to_query(), query);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use crate::{Label, LabeledData, SubLabel, TopLabel};
+
+use super::bit_set::BitSet;
+
+/// the items which have a label
+/// labels which only few items have store the indices of the items to save memory
+#[derive(Clone)]
+pub(crate) enum ItemSet {
+    Dense(BitSet),
+    Sparse(Vec<u32>),
+}
+
+impl ItemSet {
+    /// adds the items which are appended to the data, len is the new number of items
+    /// new_items have to be sorted and greater than the items of the set
+    fn extend(&mut self, new_items: &[u32], len: usize) {
+        match self {
+            ItemSet::Dense(items) => {
+                items.grow(len);
+                new_items
+                    .iter()
+                    .for_each(|&item| items.insert(item as usize));
+            }
+            ItemSet::Sparse(items) => {
+                items.extend_from_slice(new_items);
+                //a bitset needs one bit per item and an index 32 bits per item
+                if items.len() * 32 >= len {
+                    let mut set = BitSet::empty(len);
+                    items.iter().for_each(|&item| set.insert(item as usize));
+                    *self = ItemSet::Dense(set);
+                }
+            }
+        }
+    }
+
+    /// the number of items which are also in set
+    pub(crate) fn count_in(&self, set: &BitSet) -> u32 {
+        match self {
+            ItemSet::Dense(items) => items.intersection_count(set),
+            ItemSet::Sparse(items) => items
+                .iter()
+                .filter(|&&item| set.contains(item as usize))
+                .count() as u32,
+        }
+    }
+
+    /// the number of items which are also in other and in set
+    pub(crate) fn intersection_count_in(&self, other: &ItemSet, set: &BitSet) -> u32 {
+        match (self, other) {
+            (ItemSet::Dense(items), ItemSet::Dense(other_items)) => {
+                items.intersection_count_of_three(other_items, set)
+            }
+            //the sorted indices are walked together
+            (ItemSet::Sparse(items), ItemSet::Sparse(other_items)) => {
+                let mut other_items = other_items.iter().peekable();
+                items
+                    .iter()
+                    .filter(|&&item| {
+                        while other_items
+                            .next_if(|&&other_item| other_item < item)
+                            .is_some()
+                        {}
+                        other_items.peek() == Some(&&item) && set.contains(item as usize)
+                    })
+                    .count() as u32
+            }
+            (ItemSet::Sparse(items), ItemSet::Dense(dense))
+            | (ItemSet::Dense(dense), ItemSet::Sparse(items)) => items
+                .iter()
+                .filter(|&&item| dense.contains(item as usize) && set.contains(item as usize))
+                .count() as u32,
+        }
+    }
+
+    /// adds the items to set
+    pub(crate) fn add_to(&self, set: &mut BitSet) {
+        match self {
+            ItemSet::Dense(items) => set.or(items),
+            ItemSet::Sparse(items) => items.iter().for_each(|&item| set.insert(item as usize)),
+        }
+    }
+
+    pub(crate) fn to_bit_set(&self, len: usize) -> BitSet {
+        match self {
+            ItemSet::Dense(items) => items.clone(),
+            ItemSet::Sparse(_) => {
+                let mut set = BitSet::empty(len);
+                self.add_to(&mut set);
+                set
+            }
+        }
+    }
+}
+
+/// maps every distinct label of the data to an id and stores which items have it
+/// the index is built once per dataset so the filters can be calculated with bitsets
+#[derive(Clone)]
+pub(crate) struct LabelIndex<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// the number of items
+    len: usize,
+    /// combines the versions of the items, see [`LabeledData::version`]
+    fingerprint: u64,
+    labels: Vec<L>,
+    ids: HashMap<L, u32>,
+    /// the top level group of every label
+    /// TopLabel is not Hash so every top level label is represented by its first label
+    top_level_groups: Vec<usize>,
+    top_level_labels: Vec<u32>,
+    items: Vec<ItemSet>,
+    top_level_items: Vec<ItemSet>,
+    marker_0: PhantomData<TL>,
+    marker_1: PhantomData<SL>,
+}
+
+impl<L, TL, SL> LabelIndex<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// returns if the index was built from data, this calls [`LabeledData::version`] for every item
+    pub(crate) fn is_index_of<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> bool {
+        self.len == data.len() && self.fingerprint == fingerprint(FINGERPRINT_START, data)
+    }
+
+    /// returns if data starts with the data of the index
+    pub(crate) fn is_prefix_of<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> bool {
+        self.len <= data.len()
+            && self.fingerprint == fingerprint(FINGERPRINT_START, &data[..self.len])
+    }
+
+    /// all distinct labels of the data, the index of a label is its id
+    pub(crate) fn labels(&self) -> &[L] {
+        &self.labels
+    }
+
+    pub(crate) fn id(&self, label: &L) -> Option<u32> {
+        self.ids.get(label).copied()
+    }
+
+    /// the items which have the label with the id
+    pub(crate) fn items(&self, id: u32) -> &ItemSet {
+        &self.items[id as usize]
+    }
+
+    pub(crate) fn top_level_group_of(&self, id: u32) -> usize {
+        self.top_level_groups[id as usize]
+    }
+
+    /// returns the top level group of the label even if the label is not in the data
+    pub(crate) fn top_level_group(&self, label: &L) -> Option<usize> {
+        match self.id(label) {
+            Some(id) => Some(self.top_level_group_of(id)),
+            None => self.top_level_labels.iter().position(|&first| {
+                self.labels[first as usize].get_top_level_label() == label.get_top_level_label()
+            }),
+        }
+    }
+
+    pub(crate) fn number_of_top_level_groups(&self) -> usize {
+        self.top_level_labels.len()
+    }
+
+    /// the items which have any label of the top level group
+    pub(crate) fn top_level_items(&self, group: usize) -> &ItemSet {
+        &self.top_level_items[group]
+    }
+}
+
+impl<L, TL, SL> LabelIndex<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// adds the items of data which are not in the index yet
+    /// the items which are already in the index have to be unchanged
+    pub(crate) fn append<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        let new_data = &data[self.len..];
+        let mut new_items: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut new_top_level_items: HashMap<usize, Vec<u32>> = HashMap::new();
+
+        for (item, data) in new_data.iter().enumerate() {
+            let item = (self.len + item) as u32;
+            for label in data.get_labels() {
+                let id = match self.ids.get(label) {
+                    Some(&id) => id,
+                    None => self.insert_label(label),
+                };
+
+                //every label is just counted once per item
+                let label_items = new_items.entry(id).or_default();
+                if label_items.last() != Some(&item) {
+                    label_items.push(item);
+                }
+                let group_items = new_top_level_items
+                    .entry(self.top_level_groups[id as usize])
+                    .or_default();
+                if group_items.last() != Some(&item) {
+                    group_items.push(item);
+                }
+            }
+        }
+
+        let len = data.len();
+        for (id, items) in self.items.iter_mut().enumerate() {
+            let new_items = new_items.get(&(id as u32)).map(Vec::as_slice);
+            items.extend(new_items.unwrap_or_default(), len);
+        }
+        for (group, items) in self.top_level_items.iter_mut().enumerate() {
+            let new_items = new_top_level_items.get(&group).map(Vec::as_slice);
+            items.extend(new_items.unwrap_or_default(), len);
+        }
+        self.fingerprint = fingerprint(self.fingerprint, new_data);
+        self.len = len;
+    }
+
+    /// adds a label which is not in the index yet and returns its id
+    fn insert_label(&mut self, label: &L) -> u32 {
+        let id = self.labels.len() as u32;
+        let group = self
+            .top_level_labels
+            .iter()
+            .position(|&first| {
+                self.labels[first as usize].get_top_level_label() == label.get_top_level_label()
+            })
+            .unwrap_or_else(|| {
+                self.top_level_labels.push(id);
+                self.top_level_items.push(ItemSet::Sparse(Vec::new()));
+                self.top_level_labels.len() - 1
+            });
+        self.labels.push(label.clone());
+        self.ids.insert(label.clone(), id);
+        self.top_level_groups.push(group);
+        self.items.push(ItemSet::Sparse(Vec::new()));
+        id
+    }
+}
+
+impl<D, L, TL, SL> From<&[D]> for LabelIndex<L, TL, SL>
+where
+    D: LabeledData<L, TL, SL>,
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    fn from(data: &[D]) -> Self {
+        let mut index = Self {
+            len: 0,
+            fingerprint: FINGERPRINT_START,
+            labels: Vec::new(),
+            ids: HashMap::new(),
+            top_level_groups: Vec::new(),
+            top_level_labels: Vec::new(),
+            items: Vec::new(),
+            top_level_items: Vec::new(),
+            marker_0: PhantomData,
+            marker_1: PhantomData,
+        };
+        index.append(data);
+        index
+    }
+}
+
+const FINGERPRINT_START: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// combines the versions of data with the fingerprint of the data before it
+fn fingerprint<D, L, TL, SL>(start: u64, data: &[D]) -> u64
+where
+    D: LabeledData<L, TL, SL>,
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    data.iter().fold(start, |fingerprint, data| {
+        (fingerprint ^ data.version()).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+
+use crate::{
+    filter_group::{FilterGroup, FilterNode, GroupOperator},
+    query::print_filter,
+    sub_filter::{LabelOptions, Matching, SubFilter, SubFilterCore},
+    CoOccurrence, FunnelStep, Label, LabelDistribution, SubLabel, TopLabel,
+};
+
+pub(crate) use self::thread_communicator::ThreadCommunicator;
+pub use self::{bit_set::BitSet, worker_pool::WorkerPool};
+pub(crate) use self::{filter_cache::FilterCache, label_index::LabelIndex};
+use self::{set_counter::SetCounter, work::Work};
+
+/// the number of threads if no other number is configured
+pub(crate) const DEFAULT_NUMBER_OF_THREADS: usize = 7;
+
+mod bit_set;
+mod filter_cache;
+mod label_index;
+mod set_counter;
+mod thread_communicator;
+mod work;
+mod work_state;
+mod worker_pool;
+
+/// the result of the [`Work::MainFilterOptins`]
+#[derive(Clone)]
+pub(crate) struct MainOptions<L> {
+    /// the top level labels of filters which can be added together with the number of data which is left then
+    pub(crate) label_options: Vec<(L, u32)>,
+    pub(crate) distribution: LabelDistribution<L>,
+    /// None if it was not counted
+    pub(crate) co_occurrence: Option<CoOccurrence<L>>,
+    pub(crate) funnel: Vec<FunnelStep>,
+}
+
+impl<L> Default for MainOptions<L> {
+    fn default() -> Self {
+        Self {
+            label_options: Vec::new(),
+            distribution: LabelDistribution::default(),
+            co_occurrence: None,
+            funnel: Vec::new(),
+        }
+    }
+}
+
+/// calculates the label options of the work and pushes them to the manager
+/// stops early if the calculation of the generation is discarded
+fn calculate_filter_options<L, TL, SL>(
+    manager: &ThreadCommunicator<L, TL, SL>,
+    work: Work<L, TL, SL>,
+    generation: u64,
+) where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let (filter, context, index, co_occurrence, all_filters) = match work {
+        Work::FilterLabel {
+            filter,
+            context,
+            index,
+        } => (Some(filter), context, index, false, None),
+
+        Work::MainFilterOptins {
+            context,
+            filters,
+            index,
+            co_occurrence,
+        } => (None, context, index, co_occurrence, Some(filters)),
+    };
+
+    //get the current label to calculate the usefull sub level labels
+    let current_label = filter.as_ref().map(|f| f.label().clone());
+    let current_group = current_label
+        .as_ref()
+        .and_then(|label| index.top_level_group(label));
+
+    //the data is just relevant if the result of the filter tree depends on the filter
+    //for the main filter options this means that the data is not filtered out
+    let relevant = &context.relevant;
+    //counts the data which is not filtered out whatever the filter returns
+    let passing_counter = context.passing;
+    let data_counter = relevant.count();
+
+    if manager.is_outdated(generation) {
+        return;
+    }
+
+    //counts the not filtered out data which has any label with the current top level label
+    let any_sub_level_label_counter = current_group
+        .map(|group| index.top_level_items(group).count_in(relevant))
+        .unwrap_or(0);
+    //counts the not filtered out data which has any label with the top level label
+    let top_level_label_counter: Vec<u32> = (0..index.number_of_top_level_groups())
+        .map(|group| index.top_level_items(group).count_in(relevant))
+        .collect();
+    //for set filters it depends on the set which labels are usefull
+    let set_counter = match filter.as_ref().map(|f| f.matching()) {
+        Some(Matching::Set { labels, all }) => {
+            Some(SetCounter::new(labels, *all, relevant, &index))
+        }
+        _ => None,
+    };
+
+    //the number of data which is left if the filter is set to an option
+    //where counter is the number of not filtered out data which matches the filter then
+    //in a not group the relevant data is left if the filter does not match
+    let inverted = filter.as_ref().map(|f| f.inverted()).unwrap_or(false) != context.negated;
+    let remaining = |counter: u32| {
+        if inverted {
+            passing_counter + data_counter - counter
+        } else {
+            passing_counter + counter
+        }
+    };
+
+    //get the usefull labels
+    //these are those which are in some but not all Trajectories
+    let mut usefull_top_level_labels: Vec<(L, u32)> = Vec::new();
+    let mut usefull_sub_level_labels: Vec<(L, u32)> = Vec::new();
+
+    let mut useless_top_level_labels: Vec<(L, u32)> = Vec::new();
+    let mut useless_sub_level_labels: Vec<(L, u32)> = Vec::new();
+
+    //for the main filter options the pass also counts how the not filtered out data spreads over the labels
+    let mut distribution = filter.is_none().then(|| LabelDistribution {
+        passing: data_counter,
+        ..LabelDistribution::default()
+    });
+    //every top level label is represented by its first label
+    let mut top_level_label_seen = vec![false; top_level_label_counter.len()];
+
+    for (id, label) in index.labels().iter().enumerate() {
+        if manager.is_outdated(generation) {
+            return;
+        }
+
+        let id = id as u32;
+        let items = index.items(id);
+        let not_filtered_out_counter = items.count_in(relevant);
+        if let Some(distribution) = distribution.as_mut() {
+            if not_filtered_out_counter != 0 {
+                distribution
+                    .labels
+                    .push((label.clone(), not_filtered_out_counter));
+            }
+        }
+        let group = index.top_level_group_of(id);
+        match current_group {
+            Some(current_group) if group == current_group => {
+                //for set filters the option toggles the label in the set
+                let (is_usefull, counter) = match set_counter.as_ref() {
+                    Some(set_counter) => (
+                        set_counter.is_usefull(label, items),
+                        set_counter.count_toggled(label, items),
+                    ),
+                    None => (
+                        not_filtered_out_counter != 0 && not_filtered_out_counter != data_counter,
+                        not_filtered_out_counter,
+                    ),
+                };
+                if !is_usefull {
+                    if !useless_sub_level_labels.iter().any(|(useless_label, _c)| {
+                        useless_label.get_sub_level_label() == label.get_sub_level_label()
+                    }) {
+                        useless_sub_level_labels.push((label.clone(), remaining(counter)));
+                    }
+                } else if !usefull_sub_level_labels.iter().any(|(usefull_label, _c)| {
+                    usefull_label.get_sub_level_label() == label.get_sub_level_label()
+                }) {
+                    usefull_sub_level_labels.push((label.clone(), remaining(counter)));
+                }
+            }
+
+            //a top level option selects any label with the top level label
+            _ if !top_level_label_seen[group] => {
+                top_level_label_seen[group] = true;
+                let counter = top_level_label_counter[group];
+                if counter == 0 || counter == data_counter {
+                    useless_top_level_labels.push((label.clone(), remaining(counter)));
+                } else {
+                    usefull_top_level_labels.push((label.clone(), remaining(counter)));
+                }
+                if let Some(distribution) = distribution.as_mut() {
+                    if counter != 0 {
+                        distribution.top_level_labels.push((label.clone(), counter));
+                    }
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    usefull_top_level_labels.sort();
+    useless_top_level_labels.sort();
+    usefull_sub_level_labels.sort();
+    useless_sub_level_labels.sort();
+
+    if let Some(core) = filter {
+        //we had the FilterLabel work
+        let options = LabelOptions {
+            usefull_top_level_labels,
+            useless_top_level_labels,
+            usefull_sub_level_labels,
+            useless_sub_level_labels,
+            any_sub_level_label_usefull: any_sub_level_label_counter != 0
+                && any_sub_level_label_counter != data_counter,
+            any_sub_level_label_count: remaining(any_sub_level_label_counter),
+            passing_other_filters: context.passing_other_filters,
+            passing_all_filters: context.passing_all_filters,
+        };
+        let filter = SubFilter::from_core_with_label_options(core, options);
+        manager.push_finished_filter(filter, generation);
+    } else {
+        //we had the MainFilterOptins work
+        assert!(usefull_sub_level_labels.is_empty());
+        let mut distribution = distribution.expect("the distribution to be counted");
+        distribution.sort();
+        let co_occurrence = if co_occurrence {
+            match count_co_occurrence(&index, relevant, || manager.is_outdated(generation)) {
+                Some(co_occurrence) => Some(co_occurrence),
+                None => return,
+            }
+        } else {
+            None
+        };
+        let all_filters = all_filters.expect("the filters of the funnel");
+        if manager.is_outdated(generation) {
+            return;
+        }
+        let funnel = count_funnel(&all_filters, &index, relevant);
+        let main_options = MainOptions {
+            label_options: usefull_top_level_labels,
+            distribution,
+            co_occurrence,
+            funnel,
+        };
+        manager.push_main_filter_label_options(main_options, generation)
+    }
+    //the progress changed
+    manager.request_repaint();
+}
+
+/// evaluates the filter tree for every data, leaf returns the data which passes a filter
+/// returns None if no filter in the tree had a result, like [`FilterGroup::evaluate`]
+pub(crate) fn evaluate_items<F, C>(group: &FilterGroup<F>, leaf: &mut C) -> Option<BitSet>
+where
+    C: FnMut(&F) -> Option<BitSet>,
+{
+    let mut result: Option<BitSet> = None;
+    for child in group.children() {
+        let items = match child {
+            FilterNode::Filter(filter) => leaf(filter),
+            FilterNode::Group(group) => evaluate_items(group, leaf),
+        };
+        match (result.as_mut(), items) {
+            (_, None) => (),
+            (None, items) => result = items,
+            (Some(result), Some(items)) => match group.operator() {
+                GroupOperator::And => result.and(&items),
+                GroupOperator::Or | GroupOperator::Not => result.or(&items),
+            },
+        }
+    }
+
+    //none of the children may match
+    if group.operator() == GroupOperator::Not {
+        if let Some(result) = result.as_mut() {
+            result.invert();
+        }
+    }
+    result
+}
+
+/// the items which pass the tree if a node passes every item, no item or is not active
+pub(crate) struct Outcomes {
+    pub(crate) if_passing: BitSet,
+    pub(crate) if_failing: BitSet,
+    pub(crate) if_inactive: BitSet,
+}
+
+/// calls f for every filter of group (depth first) with its outcomes
+/// outcomes are those of group and leaf returns the items which pass a filter, None if it is not active
+///
+/// the children of a group are combined with the outcomes of the group in one pass,
+/// so the effort grows linearly with the number of filters
+pub(crate) fn for_each_outcome<F, C, O>(
+    group: &FilterGroup<F>,
+    outcomes: &Outcomes,
+    leaf: &mut C,
+    f: &mut O,
+) where
+    C: FnMut(&F) -> Option<BitSet>,
+    O: FnMut(&F, Outcomes),
+{
+    let len = outcomes.if_passing.len();
+    let combine = |a: Option<BitSet>, b: &Option<BitSet>| match (a, b) {
+        (a, None) => a,
+        (None, b) => b.clone(),
+        (Some(mut a), Some(b)) => {
+            match group.operator() {
+                GroupOperator::And => a.and(b),
+                GroupOperator::Or | GroupOperator::Not => a.or(b),
+            }
+            Some(a)
+        }
+    };
+
+    let children: Vec<Option<BitSet>> = group
+        .children()
+        .iter()
+        .map(|child| match child {
+            FilterNode::Filter(filter) => leaf(filter),
+            FilterNode::Group(group) => evaluate_items(group, leaf),
+        })
+        .collect();
+    //the combination of the children after every child
+    let mut suffixes = vec![None; children.len()];
+    for i in (1..children.len()).rev() {
+        suffixes[i - 1] = combine(suffixes[i].clone(), &children[i]);
+    }
+
+    let mut prefix = None;
+    for ((child, items), suffix) in group.children().iter().zip(&children).zip(suffixes) {
+        let others = combine(prefix.clone(), &suffix);
+        //the outcome of the tree if the items of the group are those of others together with child
+        let outcome = |child: Option<BitSet>| match combine(others.clone(), &child) {
+            Some(mut items) => {
+                if group.operator() == GroupOperator::Not {
+                    items.invert();
+                }
+                BitSet::select(&items, &outcomes.if_passing, &outcomes.if_failing)
+            }
+            None => outcomes.if_inactive.clone(),
+        };
+        let child_outcomes = Outcomes {
+            if_passing: outcome(Some(BitSet::full(len))),
+            if_failing: outcome(Some(BitSet::empty(len))),
+            if_inactive: outcome(None),
+        };
+        match child {
+            FilterNode::Filter(filter) => f(filter, child_outcomes),
+            FilterNode::Group(group) => for_each_outcome(group, &child_outcomes, leaf, f),
+        }
+        prefix = combine(prefix, items);
+    }
+}
+
+/// counts for every filter how much data passes the other filters, see [`FilterEngine::funnel`](crate::FilterEngine::funnel)
+/// passing is the data which passes all filters
+fn count_funnel<L, TL, SL>(
+    filters: &FilterGroup<SubFilterCore<L, TL, SL>>,
+    index: &LabelIndex<L, TL, SL>,
+    passing: &BitSet,
+) -> Vec<FunnelStep>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let len = index.len();
+    let leaves: HashMap<u32, Option<BitSet>> = filters
+        .filters()
+        .map(|filter| (filter.id(), filter_items(filter, index)))
+        .collect();
+    let root = Outcomes {
+        if_passing: BitSet::full(len),
+        if_failing: BitSet::empty(len),
+        if_inactive: BitSet::full(len),
+    };
+    let passing_all_filters = passing.count();
+    let mut funnel = Vec::new();
+    for_each_outcome(
+        filters,
+        &root,
+        &mut |filter| leaves[&filter.id()].clone(),
+        &mut |filter, outcomes| {
+            let passing_other_filters = outcomes.if_inactive.count();
+            funnel.push(FunnelStep {
+                filter: print_filter(filter),
+                passing_other_filters,
+                passing_all_filters,
+                removed: passing_other_filters as i64 - passing_all_filters as i64,
+            });
+        },
+    );
+    funnel
+}
+
+/// the data which passes the filter, None if the filter is not active
+pub(crate) fn filter_items<L, TL, SL>(
+    filter: &SubFilterCore<L, TL, SL>,
+    index: &LabelIndex<L, TL, SL>,
+) -> Option<BitSet>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    if !filter.active() {
+        return None;
+    }
+
+    let len = index.len();
+    let label_items = |label: &L| match index.id(label) {
+        Some(id) => index.items(id).to_bit_set(len),
+        None => BitSet::empty(len),
+    };
+    let mut items = match filter.matching() {
+        Matching::Exact => label_items(filter.label()),
+        Matching::AnySubLabel => match index.top_level_group(filter.label()) {
+            Some(group) => index.top_level_items(group).to_bit_set(len),
+            None => BitSet::empty(len),
+        },
+        Matching::Set { labels, all: false } => {
+            let mut items = BitSet::empty(len);
+            for id in labels.iter().filter_map(|label| index.id(label)) {
+                index.items(id).add_to(&mut items);
+            }
+            items
+        }
+        Matching::Set { labels, all: true } => {
+            let mut items = BitSet::full(len);
+            for label in labels {
+                items.and(&label_items(label));
+            }
+            items
+        }
+    };
+
+    if filter.inverted() {
+        items.invert();
+    }
+    Some(items)
+}
+
+/// counts for every pair of labels the data in passing which has both
+/// just the labels which some data in passing has are counted, sorted like the labels
+/// returns None if is_outdated returns true, it is checked for every row of the matrix
+pub(crate) fn count_co_occurrence<L, TL, SL>(
+    index: &LabelIndex<L, TL, SL>,
+    passing: &BitSet,
+    is_outdated: impl Fn() -> bool,
+) -> Option<CoOccurrence<L>>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    //the items of the index are intersected directly so no label needs a bitset of its own
+    let mut label_ids: Vec<(u32, u32)> = (0..index.labels().len() as u32)
+        .filter_map(|id| {
+            let count = index.items(id).count_in(passing);
+            (count != 0).then_some((id, count))
+        })
+        .collect();
+    //labels with the same top level label are next to each other
+    label_ids
+        .sort_by(|(a, _), (b, _)| index.labels()[*a as usize].cmp(&index.labels()[*b as usize]));
+
+    let n = label_ids.len();
+    let mut counts = vec![0; n * n];
+    for (i, &(id, count)) in label_ids.iter().enumerate() {
+        if is_outdated() {
+            return None;
+        }
+        counts[i * n + i] = count;
+        for (j, &(other_id, _other_count)) in label_ids.iter().enumerate().skip(i + 1) {
+            let both = index
+                .items(id)
+                .intersection_count_in(index.items(other_id), passing);
+            counts[i * n + j] = both;
+            counts[j * n + i] = both;
+        }
+    }
+
+    let labels = label_ids
+        .into_iter()
+        .map(|(id, count)| (index.labels()[id as usize].clone(), count))
+        .collect();
+    Some(CoOccurrence::new(passing.count(), labels, counts))
+}
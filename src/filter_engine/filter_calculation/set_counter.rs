@@ -0,0 +1,121 @@
+use crate::{Label, SubLabel, TopLabel};
+
+use super::{
+    bit_set::BitSet,
+    label_index::{ItemSet, LabelIndex},
+};
+
+/// finds for a set filter which labels would change the result if they were added to or removed from the set
+///
+/// for every item the number m of set labels it has is used
+/// * one of: adding a label changes items with m == 0, removing a label changes items with m == 1
+/// * all of: adding a label changes items with m == len which do not have it,
+///   removing a label changes items with m == len - 1 which do not have it
+pub(super) struct SetCounter<'a, L> {
+    set: &'a [L],
+    all: bool,
+    /// the number of relevant items which match the set
+    matching_count: u32,
+    /// the items which can change if a label is added
+    adding: BitSet,
+    adding_count: u32,
+    /// the items which can change if a label is removed
+    removing: BitSet,
+    removing_count: u32,
+}
+
+impl<'a, L> SetCounter<'a, L> {
+    /// relevant are the items whose result depends on the filter
+    pub(super) fn new<TL, SL>(
+        set: &'a [L],
+        all: bool,
+        relevant: &BitSet,
+        index: &LabelIndex<L, TL, SL>,
+    ) -> Self
+    where
+        L: Label<TL, SL>,
+        TL: TopLabel,
+        SL: SubLabel,
+    {
+        let len = index.len();
+        //the items which have (or for all of miss) at least one and at least two set labels
+        let mut at_least_one = BitSet::empty(len);
+        let mut at_least_two = BitSet::empty(len);
+        for label in set {
+            let mut items = match index.id(label) {
+                Some(id) => index.items(id).to_bit_set(len),
+                None => BitSet::empty(len),
+            };
+            if all {
+                items.invert();
+            }
+            let mut both = items.clone();
+            both.and(&at_least_one);
+            at_least_two.or(&both);
+            at_least_one.or(&items);
+        }
+
+        //one of: m == 0 and m == 1, all of: no label missing and one label missing
+        let mut adding = relevant.clone();
+        adding.and_not(&at_least_one);
+        let mut removing = relevant.clone();
+        removing.and(&at_least_one);
+        removing.and_not(&at_least_two);
+
+        //one of: the items with a set label, all of: the items which miss no set label
+        let matching_count = if all {
+            adding.count()
+        } else {
+            relevant.count() - adding.count()
+        };
+
+        Self {
+            set,
+            all,
+            matching_count,
+            adding_count: adding.count(),
+            adding,
+            removing_count: removing.count(),
+            removing,
+        }
+    }
+
+    /// returns if adding label to or removing label from the set would change the result
+    /// items are the items which have the label
+    pub(super) fn is_usefull(&self, label: &L, items: &ItemSet) -> bool
+    where
+        L: PartialEq,
+    {
+        let (candidates, count) = if self.set.contains(label) {
+            (&self.removing, self.removing_count)
+        } else {
+            (&self.adding, self.adding_count)
+        };
+        let with_label = items.count_in(candidates);
+        if self.all {
+            with_label < count
+        } else {
+            with_label > 0
+        }
+    }
+
+    /// returns the number of relevant items which match the set if label is added to or removed from it
+    /// items are the items which have the label
+    pub(super) fn count_toggled(&self, label: &L, items: &ItemSet) -> u32
+    where
+        L: PartialEq,
+    {
+        match (self.set.contains(label), self.all) {
+            //adds the items with no set label which have the label
+            (false, false) => self.matching_count + items.count_in(&self.adding),
+            //loses the items whose only set label is the label
+            (true, false) => self.matching_count - items.count_in(&self.removing),
+            //keeps the matching items which also have the label
+            (false, true) => items.count_in(&self.adding),
+            //adds the items which just miss the label
+            (true, true) => {
+                self.matching_count + self.removing_count - items.count_in(&self.removing)
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    mem::{replace, take},
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, Weak,
+    },
+};
+
+use crate::{filter_group::FilterGroup, sub_filter::SubFilter, Label, SubLabel, TopLabel};
+
+use super::{
+    calculate_filter_options,
+    filter_cache::Delta,
+    work::Work,
+    work_state::WorkState,
+    worker_pool::{Job, PoolClient, PoolShared, WorkerPool},
+    MainOptions,
+};
+
+pub(crate) struct ThreadCommunicator<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    work_state: Mutex<WorkState<L, TL, SL>>,
+    /// is incremented whenever the current calculation is discarded
+    /// so the threads can stop outdated work without locking the work state
+    generation: AtomicU64,
+    condvar: Condvar,
+    /// called by the threads when the progress changed, e.g. to repaint the ui
+    repaint: Mutex<Option<RepaintCallback>>,
+    /// the pool whose threads do the work
+    /// without a pool the calculation runs in the thread which starts it
+    pool: Option<Arc<PoolShared>>,
+}
+
+pub(crate) type RepaintCallback = Box<dyn Fn() + Send + Sync>;
+
+impl<L, TL, SL> ThreadCommunicator<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) fn new(pool: Option<&WorkerPool>) -> Arc<Self> {
+        let tc = Arc::new(Self {
+            work_state: Mutex::new(WorkState::NothingToDo),
+            generation: AtomicU64::new(0),
+            condvar: Condvar::new(),
+            repaint: Mutex::new(None),
+            pool: pool.map(|pool| pool.shared()),
+        });
+        if let Some(pool) = &tc.pool {
+            let client: Weak<dyn PoolClient> = Arc::downgrade(&tc) as Weak<Self>;
+            pool.register(client);
+        }
+
+        tc
+    }
+
+    /// discards the current calculation, the results of running work are ignored
+    pub(crate) fn shutdown(&self) {
+        let mut current_work_state = self.work_state.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        *current_work_state = WorkState::Shutdown;
+        self.condvar.notify_all();
+    }
+
+    /// returns the work together with the generation of the calculation it belongs to
+    /// returns None if there is no work left
+    pub(crate) fn get_work(&self) -> Option<(u64, Work<L, TL, SL>)> {
+        let mut current_work_state = self.work_state.lock().unwrap();
+        if let WorkState::Working {
+            generation,
+            unfinished_work,
+            running_work,
+            ..
+        } = current_work_state.deref_mut()
+        {
+            let work = unfinished_work.pop()?;
+            *running_work += 1;
+            Some((*generation, work))
+        } else {
+            None
+        }
+    }
+
+    /// returns if the calculation of the generation was discarded
+    /// the threads check this regularly to stop outdated work early
+    pub(crate) fn is_outdated(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::Relaxed) != generation
+    }
+
+    /// sets the state to finished if all work is done
+    fn finish_if_done(&self, current_work_state: &mut WorkState<L, TL, SL>) {
+        if let WorkState::Working {
+            unfinished_work,
+            running_work,
+
+            all_filters,
+            finished_filters,
+            finished_main_filter_label,
+            ..
+        } = current_work_state
+        {
+            if !unfinished_work.is_empty() || *running_work != 0 {
+                return;
+            }
+
+            let mut finished = take(finished_filters);
+            let main_filter_label_options = take(finished_main_filter_label);
+
+            //the filters are looked up by their id so the tree keeps the order it had when the calculation started
+            let filter = all_filters.map(&mut |core| {
+                finished
+                    .remove(&core.id())
+                    .expect("every filter of the tree to be finished")
+            });
+
+            let new_state = WorkState::Finished {
+                filter,
+                main_filter_label_options,
+            };
+            *current_work_state = new_state;
+            //wakes up the threads which wait for the result
+            self.condvar.notify_all();
+            self.request_repaint();
+        }
+    }
+
+    pub(crate) fn set_repaint_callback(&self, callback: RepaintCallback) {
+        *self.repaint.lock().unwrap() = Some(callback);
+    }
+
+    pub(crate) fn request_repaint(&self) {
+        if let Some(repaint) = self.repaint.lock().unwrap().as_ref() {
+            repaint();
+        }
+    }
+
+    /// the options are ignored if the calculation of the generation was discarded in the meantime
+    pub(crate) fn push_main_filter_label_options(
+        &self,
+        main_filter_label_options: MainOptions<L>,
+        generation: u64,
+    ) {
+        let mut current_work_state = self.work_state.lock().unwrap();
+        if let WorkState::Working {
+            generation: current_generation,
+            running_work,
+            finished_main_filter_label,
+            ..
+        } = current_work_state.deref_mut()
+        {
+            if *current_generation == generation {
+                *running_work -= 1;
+                *finished_main_filter_label = main_filter_label_options;
+                self.finish_if_done(current_work_state.deref_mut());
+            }
+        }
+    }
+
+    /// the filter is ignored if the calculation of the generation was discarded in the meantime
+    pub(crate) fn push_finished_filter(&self, filter: SubFilter<L, TL, SL>, generation: u64) {
+        let mut current_work_state = self.work_state.lock().unwrap();
+        if let WorkState::Working {
+            generation: current_generation,
+            running_work,
+            finished_filters,
+            ..
+        } = current_work_state.deref_mut()
+        {
+            if *current_generation == generation {
+                *running_work -= 1;
+                finished_filters.insert(filter.id(), filter);
+                self.finish_if_done(current_work_state.deref_mut());
+            }
+        }
+    }
+
+    /// starts the work of the delta, the filters which are already finished are just taken over
+    pub(crate) fn start(&self, delta: Delta<L, TL, SL>) {
+        let Delta {
+            all_filters,
+            work: unfinished_work,
+            finished_filters,
+            finished_main_filter_label,
+        } = delta;
+
+        let mut current_work_state = self.work_state.lock().unwrap();
+        //the running work of the last calculation stops as soon as it notices the new generation
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        *current_work_state = WorkState::Working {
+            generation,
+            all_filters_len: all_filters.filters().count(),
+            all_filters,
+            unfinished_work,
+            running_work: 0,
+
+            finished_filters,
+            finished_main_filter_label: finished_main_filter_label.unwrap_or_default(),
+        };
+        //nothing may have changed
+        self.finish_if_done(current_work_state.deref_mut());
+
+        drop(current_work_state);
+
+        match &self.pool {
+            Some(pool) => pool.notify(),
+            None => self.calculate_inline(),
+        }
+    }
+
+    /// does all the work in the current thread
+    fn calculate_inline(&self) {
+        while let Some((generation, work)) = self.get_work() {
+            calculate_filter_options(self, work, generation);
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn try_get_finished(
+        &self,
+    ) -> Option<(FilterGroup<SubFilter<L, TL, SL>>, MainOptions<L>)> {
+        let current_work_state = self.work_state.lock().unwrap();
+        Self::take_finished(current_work_state)
+    }
+
+    /// blocks until the current calculation is finished
+    /// returns None if there was no calculation or its result was already taken
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn wait_finished(
+        &self,
+    ) -> Option<(FilterGroup<SubFilter<L, TL, SL>>, MainOptions<L>)> {
+        let current_work_state = self.work_state.lock().unwrap();
+        let current_work_state = self
+            .condvar
+            .wait_while(current_work_state, |work_state| {
+                matches!(work_state, WorkState::Working { .. })
+            })
+            .unwrap();
+        Self::take_finished(current_work_state)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn take_finished(
+        mut current_work_state: MutexGuard<WorkState<L, TL, SL>>,
+    ) -> Option<(FilterGroup<SubFilter<L, TL, SL>>, MainOptions<L>)> {
+        if matches!(current_work_state.deref(), WorkState::Finished { .. }) {
+            if let WorkState::Finished {
+                filter,
+                main_filter_label_options,
+            } = replace(current_work_state.deref_mut(), WorkState::NothingToDo)
+            {
+                Some((filter, main_filter_label_options))
+            } else {
+                unreachable!()
+            }
+        } else {
+            None
+        }
+    }
+
+    ///returns a progress (a, b) which means a/b
+    pub(crate) fn get_progress(&self) -> (usize, usize) {
+        let current_work_state = self.work_state.lock().unwrap();
+        let state = current_work_state.deref();
+        match state {
+            WorkState::Working {
+                all_filters_len,
+                unfinished_work,
+                running_work,
+                ..
+            } => {
+                let b = all_filters_len + 1;
+                let a = b - (unfinished_work.len() + running_work);
+
+                (a, b)
+            }
+
+            WorkState::Finished { filter, .. } => {
+                let a = filter.filters().count() + 1;
+                (a, a)
+            }
+
+            WorkState::NothingToDo | WorkState::Shutdown => (1, 1),
+        }
+    }
+}
+
+impl<L, TL, SL> PoolClient for ThreadCommunicator<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    fn take_job(self: Arc<Self>) -> Option<Job> {
+        let (generation, work) = self.get_work()?;
+        Some(Box::new(move || {
+            calculate_filter_options(&self, work, generation)
+        }))
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use crate::{filter_group::FilterGroup, sub_filter::SubFilterCore, Label, SubLabel, TopLabel};
+
+use super::{filter_cache::FilterContext, label_index::LabelIndex};
+
+pub(crate) enum Work<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    FilterLabel {
+        filter: SubFilterCore<L, TL, SL>,
+        context: Arc<FilterContext>,
+        index: Arc<LabelIndex<L, TL, SL>>,
+    },
+
+    MainFilterOptins {
+        context: Arc<FilterContext>,
+        /// the filters the funnel is counted for
+        filters: Arc<FilterGroup<SubFilterCore<L, TL, SL>>>,
+        index: Arc<LabelIndex<L, TL, SL>>,
+        /// if the co-occurrence of the labels is counted too
+        co_occurrence: bool,
+    },
+}
@@ -0,0 +1,37 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    filter_group::FilterGroup,
+    sub_filter::{SubFilter, SubFilterCore},
+    Label, SubLabel, TopLabel,
+};
+
+use super::{work::Work, MainOptions};
+
+pub(crate) enum WorkState<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    NothingToDo,
+    /// the threads should stop
+    Shutdown,
+    Working {
+        /// the generation of the calculation, results of other generations are outdated
+        generation: u64,
+        all_filters: Arc<FilterGroup<SubFilterCore<L, TL, SL>>>,
+        all_filters_len: usize,
+        unfinished_work: Vec<Work<L, TL, SL>>,
+        /// the number of work which was taken by a thread but is not finished
+        running_work: usize,
+
+        /// the finished filters by their id
+        finished_filters: HashMap<u32, SubFilter<L, TL, SL>>,
+        finished_main_filter_label: MainOptions<L>,
+    },
+    Finished {
+        filter: FilterGroup<SubFilter<L, TL, SL>>,
+        main_filter_label_options: MainOptions<L>,
+    },
+}
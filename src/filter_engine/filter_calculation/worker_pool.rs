@@ -0,0 +1,131 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, Weak},
+    thread::{spawn, JoinHandle},
+};
+
+/// a piece of work of one client which a worker thread runs
+pub(crate) type Job = Box<dyn FnOnce() + Send>;
+
+/// something which has work for the threads of a [`WorkerPool`]
+pub(crate) trait PoolClient: Send + Sync {
+    /// returns None if the client has nothing to do
+    fn take_job(self: Arc<Self>) -> Option<Job>;
+}
+
+/// threads which calculate the label options of several filters
+///
+/// the work of all filters is scheduled round robin, filters without work do not hold any thread
+/// the threads are stopped when the last clone of the pool and the last filter using it are dropped
+#[derive(Clone)]
+pub struct WorkerPool {
+    owner: Arc<PoolOwner>,
+}
+
+impl WorkerPool {
+    /// spawns the threads of the pool, a pool has at least one thread
+    pub fn new(threads: usize) -> WorkerPool {
+        let shared = Arc::new(PoolShared {
+            state: Mutex::new(PoolState {
+                clients: Vec::new(),
+                next_client: 0,
+                shutdown: false,
+            }),
+            condvar: Condvar::new(),
+        });
+
+        let threads = (0..threads.max(1))
+            .map(|_| {
+                let shared = shared.clone();
+                spawn(move || shared.work_loop())
+            })
+            .collect();
+
+        WorkerPool {
+            owner: Arc::new(PoolOwner { shared, threads }),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.owner.threads.len()
+    }
+
+    pub(crate) fn shared(&self) -> Arc<PoolShared> {
+        self.owner.shared.clone()
+    }
+}
+
+/// the part of the pool which is shared with the threads and the clients
+pub(crate) struct PoolShared {
+    state: Mutex<PoolState>,
+    condvar: Condvar,
+}
+
+struct PoolState {
+    clients: Vec<Weak<dyn PoolClient>>,
+    /// the index of the client which gets the next thread
+    next_client: usize,
+    shutdown: bool,
+}
+
+impl PoolShared {
+    pub(crate) fn register(&self, client: Weak<dyn PoolClient>) {
+        self.state.lock().unwrap().clients.push(client);
+    }
+
+    /// wakes up the threads because a client has new work
+    pub(crate) fn notify(&self) {
+        let _state = self.state.lock().unwrap();
+        self.condvar.notify_all();
+    }
+
+    fn work_loop(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.shutdown {
+            match state.next_job() {
+                Some(job) => {
+                    drop(state);
+                    job();
+                    state = self.state.lock().unwrap();
+                }
+                None => state = self.condvar.wait(state).unwrap(),
+            }
+        }
+    }
+}
+
+impl PoolState {
+    /// asks the clients round robin for a job
+    fn next_job(&mut self) -> Option<Job> {
+        //dropped clients have no work anymore
+        self.clients.retain(|client| client.strong_count() > 0);
+
+        for _ in 0..self.clients.len() {
+            self.next_client %= self.clients.len();
+            let client = self.clients[self.next_client].upgrade();
+            self.next_client += 1;
+
+            if let Some(job) = client.and_then(|client| client.take_job()) {
+                return Some(job);
+            }
+        }
+        None
+    }
+}
+
+/// stops the threads when the last clone of the pool is dropped
+struct PoolOwner {
+    shared: Arc<PoolShared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Drop for PoolOwner {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.condvar.notify_all();
+
+        for thread in self.threads.drain(..) {
+            //a thread which panicked has nothing left to clean up
+            let _ = thread.join();
+        }
+    }
+}
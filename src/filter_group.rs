@@ -0,0 +1,506 @@
+use std::{fmt::Display, slice::Iter};
+
+#[cfg(feature = "egui")]
+use eframe::egui::{self, vec2, Button, ComboBox, CursorIcon, Id, Response, Sense, Ui};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "egui")]
+use crate::{
+    sub_filter::{FilterInfo, SubFilter},
+    Label, SubLabel, TopLabel,
+};
+
+/// how the children of a [`FilterGroup`] are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) enum GroupOperator {
+    /// all children have to match
+    And,
+    /// at least one child has to match
+    Or,
+    /// none of the children may match
+    Not,
+}
+
+impl GroupOperator {
+    #[cfg(feature = "egui")]
+    pub(crate) const ALL: [GroupOperator; 3] =
+        [GroupOperator::And, GroupOperator::Or, GroupOperator::Not];
+
+    /// combines the results of the children
+    /// children which return None (e.g. inactive filters) are ignored
+    /// returns None if no child had a result
+    fn combine(self, children: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+        let mut any_result = false;
+        for result in children.flatten() {
+            any_result = true;
+            match (self, result) {
+                (GroupOperator::And, false) => return Some(false),
+                (GroupOperator::Or, true) => return Some(true),
+                (GroupOperator::Not, true) => return Some(false),
+                _ => (),
+            }
+        }
+
+        if any_result {
+            Some(match self {
+                GroupOperator::And => true,
+                GroupOperator::Or => false,
+                GroupOperator::Not => true,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for GroupOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupOperator::And => write!(f, "AND"),
+            GroupOperator::Or => write!(f, "OR"),
+            GroupOperator::Not => write!(f, "NOT"),
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) enum FilterNode<F> {
+    Filter(F),
+    Group(FilterGroup<F>),
+}
+
+/// a node of the filter tree which combines its children with an [`GroupOperator`]
+/// the generic F is the leaf type, the ui uses SubFilter and the worker threads use SubFilterCore
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct FilterGroup<F> {
+    id: u32,
+    operator: GroupOperator,
+    children: Vec<FilterNode<F>>,
+}
+
+impl<F> FilterGroup<F> {
+    pub(crate) fn new(id: u32, operator: GroupOperator) -> Self {
+        Self {
+            id,
+            operator,
+            children: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub(crate) fn operator(&self) -> GroupOperator {
+        self.operator
+    }
+
+    pub(crate) fn children(&self) -> &[FilterNode<F>] {
+        &self.children
+    }
+
+    /// evaluates the tree, leaf is called for every filter which has to be evaluated
+    /// returns None if no filter in the tree had a result
+    pub(crate) fn evaluate<C>(&self, leaf: &mut C) -> Option<bool>
+    where
+        C: FnMut(&F) -> Option<bool>,
+    {
+        self.operator
+            .combine(self.children.iter().map(|child| match child {
+                FilterNode::Filter(filter) => leaf(filter),
+                FilterNode::Group(group) => group.evaluate(leaf),
+            }))
+    }
+
+    /// returns if other has the same operators and structure
+    /// and same_filter returns true for every pair of filters, the ids of the groups are ignored
+    pub(crate) fn same_config<C>(&self, other: &FilterGroup<F>, same_filter: &mut C) -> bool
+    where
+        C: FnMut(&F, &F) -> bool,
+    {
+        self.operator == other.operator
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .zip(other.children.iter())
+                .all(|pair| match pair {
+                    (FilterNode::Filter(a), FilterNode::Filter(b)) => same_filter(a, b),
+                    (FilterNode::Group(a), FilterNode::Group(b)) => a.same_config(b, same_filter),
+                    _ => false,
+                })
+    }
+
+    /// iterates over all filters of the tree in depth first order
+    pub(crate) fn filters(&self) -> Filters<'_, F> {
+        Filters {
+            stack: vec![self.children.iter()],
+        }
+    }
+
+    /// returns the ids of this group and all sub groups (depth first) together with their depth
+    pub(crate) fn groups(&self) -> Vec<(u32, usize)> {
+        let mut groups = Vec::new();
+        self.collect_groups(0, &mut groups);
+        groups
+    }
+
+    fn collect_groups(&self, depth: usize, groups: &mut Vec<(u32, usize)>) {
+        groups.push((self.id, depth));
+        for child in self.children.iter() {
+            if let FilterNode::Group(group) = child {
+                group.collect_groups(depth + 1, groups);
+            }
+        }
+    }
+
+    #[cfg(feature = "egui")]
+    pub(crate) fn group_mut(&mut self, id: u32) -> Option<&mut FilterGroup<F>> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| match child {
+            FilterNode::Group(group) => group.group_mut(id),
+            FilterNode::Filter(_) => None,
+        })
+    }
+
+    pub(crate) fn push(&mut self, node: FilterNode<F>) {
+        self.children.push(node);
+    }
+
+    /// returns the id of the group which directly contains the first filter (depth first)
+    /// for which predicate returns true
+    pub(crate) fn group_of<P>(&self, predicate: &mut P) -> Option<u32>
+    where
+        P: FnMut(&F) -> bool,
+    {
+        let contains = self.children.iter().any(|child| match child {
+            FilterNode::Filter(filter) => predicate(filter),
+            FilterNode::Group(_) => false,
+        });
+        if contains {
+            return Some(self.id);
+        }
+
+        self.children.iter().find_map(|child| match child {
+            FilterNode::Group(group) => group.group_of(predicate),
+            FilterNode::Filter(_) => None,
+        })
+    }
+
+    /// removes the first filter (depth first) for which predicate returns true
+    pub(crate) fn take_filter<P>(&mut self, predicate: &mut P) -> Option<F>
+    where
+        P: FnMut(&F) -> bool,
+    {
+        let position = self.children.iter().position(|child| match child {
+            FilterNode::Filter(filter) => predicate(filter),
+            FilterNode::Group(_) => false,
+        });
+
+        if let Some(position) = position {
+            if let FilterNode::Filter(filter) = self.children.remove(position) {
+                return Some(filter);
+            } else {
+                unreachable!()
+            }
+        }
+
+        self.children.iter_mut().find_map(|child| match child {
+            FilterNode::Group(group) => group.take_filter(predicate),
+            FilterNode::Filter(_) => None,
+        })
+    }
+
+    /// inserts filter in front of or after the first filter (depth first) for which predicate returns true
+    /// returns the filter if there is no such filter
+    pub(crate) fn insert_filter<P>(
+        &mut self,
+        predicate: &mut P,
+        filter: F,
+        after: bool,
+    ) -> Result<(), F>
+    where
+        P: FnMut(&F) -> bool,
+    {
+        let position = self.children.iter().position(|child| match child {
+            FilterNode::Filter(filter) => predicate(filter),
+            FilterNode::Group(_) => false,
+        });
+
+        if let Some(position) = position {
+            let position = if after { position + 1 } else { position };
+            self.children.insert(position, FilterNode::Filter(filter));
+            return Ok(());
+        }
+
+        let mut filter = filter;
+        for child in self.children.iter_mut() {
+            if let FilterNode::Group(group) = child {
+                match group.insert_filter(predicate, filter, after) {
+                    Ok(()) => return Ok(()),
+                    Err(not_inserted) => filter = not_inserted,
+                }
+            }
+        }
+        Err(filter)
+    }
+
+    /// creates a tree with the same structure where every group and filter gets a new id
+    /// the group gets id, the others get the ids from next_id on in depth first order
+    /// f creates a filter like the given one with the given id
+    pub(crate) fn renumber<M>(&self, id: u32, next_id: &mut u32, f: &mut M) -> FilterGroup<F>
+    where
+        M: FnMut(&F, u32) -> F,
+    {
+        let mut group = FilterGroup::new(id, self.operator);
+        for child in &self.children {
+            let child_id = *next_id;
+            *next_id += 1;
+            group.push(match child {
+                FilterNode::Filter(filter) => FilterNode::Filter(f(filter, child_id)),
+                FilterNode::Group(child) => FilterNode::Group(child.renumber(child_id, next_id, f)),
+            });
+        }
+        group
+    }
+
+    /// creates a tree with the same structure where every filter is mapped by f
+    pub(crate) fn map<G, M>(&self, f: &mut M) -> FilterGroup<G>
+    where
+        M: FnMut(&F) -> G,
+    {
+        FilterGroup {
+            id: self.id,
+            operator: self.operator,
+            children: self
+                .children
+                .iter()
+                .map(|child| match child {
+                    FilterNode::Filter(filter) => FilterNode::Filter(f(filter)),
+                    FilterNode::Group(group) => FilterNode::Group(group.map(f)),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub(crate) struct Filters<'a, F> {
+    stack: Vec<Iter<'a, FilterNode<F>>>,
+}
+
+impl<'a, F> Iterator for Filters<'a, F> {
+    type Item = &'a F;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(FilterNode::Filter(filter)) => return Some(filter),
+                Some(FilterNode::Group(group)) => self.stack.push(group.children.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// changes to the tree which can not be applied while the tree is shown
+#[cfg(feature = "egui")]
+pub(crate) enum GroupAction {
+    AddFilter {
+        group: u32,
+    },
+    AddGroup {
+        group: u32,
+    },
+    MoveFilter {
+        filter: u32,
+        group: u32,
+    },
+    /// moves filter in front of or after target
+    DropFilter {
+        filter: u32,
+        target: u32,
+        after: bool,
+    },
+}
+
+#[cfg(feature = "egui")]
+pub(crate) struct GroupInfo {
+    pub(crate) needs_removal: bool,
+    pub(crate) was_changed: bool,
+}
+
+#[cfg(feature = "egui")]
+impl<L, TL, SL> FilterGroup<SubFilter<L, TL, SL>>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// shows the group and all its children as rows of a grid
+    /// all_groups are the groups (id, depth) a filter can be moved to
+    pub(crate) fn show(
+        &mut self,
+        ui: &mut Ui,
+        depth: usize,
+        all_groups: &[(u32, usize)],
+        actions: &mut Vec<GroupAction>,
+    ) -> GroupInfo {
+        let mut was_changed = false;
+        //the id of the filter which is dragged, the same for all groups as they share the ui of the grid
+        let drag_id = ui.make_persistent_id("dragged_filter");
+
+        show_indentation(ui, depth);
+        let operator_changed = ComboBox::from_id_source(format!("group_operator_{}", self.id))
+            .selected_text(self.operator.to_string())
+            .width(50.0)
+            .show_ui(ui, |ui| {
+                let mut changed = false;
+                for operator in GroupOperator::ALL {
+                    changed |= ui
+                        .selectable_value(&mut self.operator, operator, operator.to_string())
+                        .changed();
+                }
+                changed
+            })
+            .inner
+            .unwrap_or(false);
+        was_changed |= operator_changed;
+
+        if ui.button("add filter").clicked() {
+            actions.push(GroupAction::AddFilter { group: self.id });
+        }
+        if ui.button("add group").clicked() {
+            actions.push(GroupAction::AddGroup { group: self.id });
+        }
+        //the root group can not be removed
+        let removed = ui
+            .add_enabled(depth > 0, Button::new("remove group"))
+            .clicked();
+        ui.end_row();
+
+        //the children which need a removal are dropped after they are shown
+        self.children.retain_mut(|child| match child {
+            FilterNode::Filter(filter) => {
+                let filter_id = filter.id();
+                let handle = show_drag_handle(ui, depth + 1);
+                if handle.drag_started() {
+                    ui.data().insert_temp(drag_id, filter_id);
+                }
+                actions.extend(show_drop_target(ui, drag_id, filter_id, &handle));
+
+                let FilterInfo {
+                    needs_removal,
+                    was_changed: filter_changed,
+                } = filter.show(ui);
+                was_changed |= filter_changed;
+
+                ComboBox::from_id_source(format!("move_filter_{}", filter_id))
+                    .selected_text("move")
+                    .width(50.0)
+                    .show_ui(ui, |ui| {
+                        for &(group, group_depth) in all_groups {
+                            let text = if group_depth == 0 {
+                                "top level".to_string()
+                            } else {
+                                format!("{}group {}", "  ".repeat(group_depth - 1), group)
+                            };
+                            if ui.selectable_label(group == self.id, text).clicked()
+                                && group != self.id
+                            {
+                                actions.push(GroupAction::MoveFilter {
+                                    filter: filter_id,
+                                    group,
+                                });
+                            }
+                        }
+                    });
+
+                ui.end_row();
+                !needs_removal
+            }
+            FilterNode::Group(group) => {
+                let GroupInfo {
+                    needs_removal,
+                    was_changed: group_changed,
+                } = group.show(ui, depth + 1, all_groups, actions);
+                was_changed |= group_changed;
+                !needs_removal
+            }
+        });
+
+        if depth == 0 {
+            let dragging = ui.data().get_temp::<u32>(drag_id).is_some();
+            //the drag also ends if the pointer is released outside of the filters
+            if dragging && !ui.input().pointer.any_down() {
+                ui.data().remove::<u32>(drag_id);
+            } else if dragging {
+                ui.output().cursor_icon = CursorIcon::Grabbing;
+            }
+        }
+
+        GroupInfo {
+            needs_removal: removed,
+            was_changed: was_changed || removed,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+fn show_indentation(ui: &mut Ui, depth: usize) {
+    ui.horizontal(|ui| {
+        ui.add_space(depth as f32 * 15.0);
+    });
+}
+
+/// shows the indentation of a filter together with the handle to drag it
+#[cfg(feature = "egui")]
+fn show_drag_handle(ui: &mut Ui, depth: usize) -> Response {
+    ui.horizontal(|ui| {
+        ui.add_space(depth as f32 * 15.0);
+        ui.add(egui::Label::new("☰").sense(Sense::drag()))
+            .on_hover_cursor(CursorIcon::Grab)
+            .on_hover_text("drag to reorder")
+    })
+    .inner
+}
+
+/// marks where the dragged filter is dropped if the pointer is over the row of target
+/// returns the action which moves it there when the pointer is released
+#[cfg(feature = "egui")]
+fn show_drop_target(ui: &Ui, drag_id: Id, target: u32, handle: &Response) -> Option<GroupAction> {
+    let dragged = ui.data().get_temp::<u32>(drag_id)?;
+    let pointer = ui.input().pointer.hover_pos()?;
+    //the row also covers half of the space to the neighbouring rows
+    let row = handle
+        .rect
+        .expand2(vec2(0.0, ui.spacing().item_spacing.y / 2.0));
+    if dragged == target || !row.y_range().contains(&pointer.y) {
+        return None;
+    }
+
+    let after = pointer.y > row.center().y;
+    let y = if after { row.bottom() } else { row.top() };
+    ui.painter().hline(
+        row.left()..=ui.max_rect().right(),
+        y,
+        ui.visuals().selection.stroke,
+    );
+
+    ui.input()
+        .pointer
+        .any_released()
+        .then_some(GroupAction::DropFilter {
+            filter: dragged,
+            target,
+            after,
+        })
+}
@@ -0,0 +1,105 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    filter_group::FilterGroup,
+    query::{parse_query, print_query},
+    sub_filter::SubFilterCore,
+    Label, QueryError, SubLabel, TopLabel,
+};
+
+/// the configuration of all filters of a [`FilterEngine`](crate::FilterEngine)
+/// without the label options which are calculated from the data
+///
+/// with the `serde` feature it can be serialized, e.g. to store it with eframe's `App::save`
+/// and to restore it with [`FilterEngine::from_state`](crate::FilterEngine::from_state)
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(serialize = "L: Serialize", deserialize = "L: Deserialize<'de>"))
+)]
+pub struct MainFilterState<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub(crate) filters: FilterGroup<SubFilterCore<L, TL, SL>>,
+}
+
+impl<L, TL, SL> MainFilterState<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    /// parses a query like `Weather:Rain AND NOT Road:Highway`, see [`query`](crate::query) for the syntax
+    /// the names of the query are resolved to the given labels through their `Display` impls
+    pub fn from_query<'l>(
+        query: &str,
+        labels: impl IntoIterator<Item = &'l L>,
+    ) -> Result<Self, QueryError>
+    where
+        L: 'l,
+    {
+        Ok(Self {
+            filters: parse_query(query, labels)?,
+        })
+    }
+
+    /// the filters where no two groups or filters have the same id
+    /// e.g. a deserialized or imported state can have duplicate ids, then all ids are given anew
+    /// otherwise the ids are kept, so the label options of unchanged filters can be reused
+    pub(crate) fn filters_with_unique_ids(&self) -> FilterGroup<SubFilterCore<L, TL, SL>> {
+        let mut ids: Vec<u32> = self
+            .filters
+            .groups()
+            .into_iter()
+            .map(|(id, _depth)| id)
+            .chain(self.filters.filters().map(|filter| filter.id()))
+            .collect();
+        let len = ids.len();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() == len {
+            return self.filters.clone();
+        }
+
+        //the root group has the id 0 like in a parsed query
+        self.filters.renumber(0, &mut 1, &mut |filter, id| {
+            SubFilterCore::new(
+                id,
+                filter.label().clone(),
+                filter.matching().clone(),
+                filter.inverted(),
+                filter.active(),
+            )
+        })
+    }
+
+    /// prints the filters in the syntax of [`MainFilterState::from_query`]
+    /// parsing the query again gives filters with the same meaning, see [`query`](crate::query)
+    pub fn to_query(&self) -> String {
+        print_query(&self.filters)
+    }
+}
+
+/// a named [`MainFilterState`] which can be applied to a [`FilterEngine`](crate::FilterEngine)
+///
+/// presets are plain data so a host app can export them, share them between users and import them again
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(serialize = "L: Serialize", deserialize = "L: Deserialize<'de>"))
+)]
+pub struct FilterPreset<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    pub name: String,
+    pub state: MainFilterState<L, TL, SL>,
+}
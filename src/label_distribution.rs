@@ -0,0 +1,153 @@
+#[cfg(feature = "egui")]
+use eframe::{
+    egui::{vec2, Button, Grid, Sense, Ui},
+    epaint::Color32,
+};
+
+#[cfg(feature = "egui")]
+use crate::sub_filter::{group_digits, Matching};
+use crate::{Label, SubLabel, TopLabel};
+
+/// the width of the bar of a label which all data has
+#[cfg(feature = "egui")]
+const BAR_WIDTH: f32 = 200.0;
+
+/// how the data which passes the filters spreads over the labels, see [`FilterEngine::label_distribution`](crate::FilterEngine::label_distribution)
+/// data with several labels is counted for each of them, labels which no data has are left out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelDistribution<L> {
+    /// the number of data which passes the filters
+    pub passing: u32,
+    /// a label for every top level label together with the number of data which has any label with this top level label,
+    /// the most frequent first
+    pub top_level_labels: Vec<(L, u32)>,
+    /// every label together with the number of data which has it, the most frequent first
+    pub labels: Vec<(L, u32)>,
+}
+
+impl<L> LabelDistribution<L> {
+    /// the labels with the same top level label as label, the most frequent first
+    pub fn sub_level_labels<'a, TL, SL>(
+        &'a self,
+        label: &'a L,
+    ) -> impl Iterator<Item = &'a (L, u32)>
+    where
+        L: Label<TL, SL>,
+        TL: TopLabel,
+        SL: SubLabel,
+    {
+        self.labels
+            .iter()
+            .filter(|(l, _count)| l.get_top_level_label() == label.get_top_level_label())
+    }
+
+    /// the most frequent first and labels with the same count in their order
+    pub(crate) fn sort(&mut self)
+    where
+        L: Ord,
+    {
+        self.top_level_labels
+            .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        self.labels
+            .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    }
+}
+
+impl<L> Default for LabelDistribution<L> {
+    fn default() -> Self {
+        Self {
+            passing: 0,
+            top_level_labels: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl<L> LabelDistribution<L> {
+    /// shows a bar for every top level label or, if drill_down is set, for every label with its top level label
+    /// returns the label and the matching of a filter for a bar which was clicked
+    pub(crate) fn show<TL, SL>(
+        &self,
+        ui: &mut Ui,
+        drill_down: &mut Option<L>,
+    ) -> Option<(L, Matching<L>)>
+    where
+        L: Label<TL, SL>,
+        TL: TopLabel,
+        SL: SubLabel,
+    {
+        let mut clicked = None;
+
+        if let Some(top_level_label) = drill_down.clone() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("⬅")
+                    .on_hover_text("all top level labels")
+                    .clicked()
+                {
+                    *drill_down = None;
+                }
+                ui.label(top_level_label.get_top_level_label().to_string());
+            });
+            Grid::new("distribution_sub_level_labels").show(ui, |ui| {
+                for (label, count) in self.sub_level_labels(&top_level_label) {
+                    let name = label
+                        .get_sub_level_label()
+                        .map(|sub_level_label| sub_level_label.to_string())
+                        .unwrap_or_default();
+                    ui.label(name);
+                    if self.show_bar(ui, *count) {
+                        clicked = Some((label.clone(), Matching::Exact));
+                    }
+                    ui.end_row();
+                }
+            });
+        } else {
+            Grid::new("distribution_top_level_labels").show(ui, |ui| {
+                for (label, count) in &self.top_level_labels {
+                    let name = label.get_top_level_label().to_string();
+                    if ui
+                        .add(Button::new(name).frame(false))
+                        .on_hover_text("shows the sub level labels")
+                        .clicked()
+                    {
+                        *drill_down = Some(label.clone());
+                    }
+                    if self.show_bar(ui, *count) {
+                        clicked = Some((label.clone(), Matching::AnySubLabel));
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+
+        if self.labels.is_empty() {
+            ui.label("no data passes the filters");
+        }
+        clicked
+    }
+
+    /// shows a bar whose width is the share of the count in the passing data
+    /// returns if it was clicked
+    fn show_bar(&self, ui: &mut Ui, count: u32) -> bool {
+        let share = count as f32 / self.passing.max(1) as f32;
+        let height = ui.spacing().interact_size.y * 0.6;
+        let (rect, response) = ui.allocate_exact_size(vec2(BAR_WIDTH, height), Sense::click());
+        let mut bar = rect;
+        bar.set_width(BAR_WIDTH * share);
+        let color = if response.hovered() {
+            ui.visuals().selection.stroke.color
+        } else {
+            ui.visuals().selection.bg_fill
+        };
+        ui.painter().rect_filled(bar, 2.0, color);
+        ui.painter()
+            .rect_stroke(rect, 2.0, (1.0, Color32::from_gray(100)));
+        ui.label(format!("{} ({:.0}%)", group_digits(count), share * 100.0));
+
+        response
+            .on_hover_text("adds a filter for the label")
+            .clicked()
+    }
+}
@@ -0,0 +1,253 @@
+use eframe::{
+    egui::{Area, Button, Frame, Id, Key, Order, RichText, ScrollArea, TextEdit, TextStyle, Ui},
+    epaint::{
+        text::{LayoutJob, TextFormat},
+        Color32,
+    },
+};
+
+use crate::sub_filter::group_digits;
+
+/// an option of a searchable popup
+pub(crate) struct SearchOption {
+    /// the text which is searched
+    pub(crate) name: String,
+    /// the number of data which is left if the option is selected
+    pub(crate) count: u32,
+    pub(crate) usefull: bool,
+    /// if the option is selected or for a checkbox if it is checked
+    pub(crate) selected: bool,
+}
+
+/// shows a button which opens a popup below it like a ComboBox
+/// unlike the popup of a ComboBox it is not closed by clicks inside it,
+/// so several checkboxes can be checked and the search field can be clicked
+/// it is closed by a click outside of it, by escape or by `ui.memory().close_popup()` in add_contents
+/// returns the result of add_contents if the popup is open
+pub(crate) fn popup_button<R>(
+    ui: &mut Ui,
+    id: Id,
+    selected_text: &str,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> Option<R> {
+    let popup_id = id.with("popup");
+    let button = ui.add(Button::new(format!("{} ⏷", selected_text)).wrap(false));
+    if button.clicked() {
+        ui.memory().toggle_popup(popup_id);
+    }
+    if !ui.memory().is_popup_open(popup_id) {
+        return None;
+    }
+
+    let area = Area::new(popup_id)
+        .order(Order::Foreground)
+        .fixed_pos(button.rect.left_bottom())
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style())
+                .show(ui, |ui| {
+                    ui.set_min_width(button.rect.width());
+                    ScrollArea::vertical()
+                        .max_height(ui.spacing().combo_height)
+                        .show(ui, add_contents)
+                        .inner
+                })
+                .inner
+        });
+
+    let clicked_outside = {
+        let input = ui.input();
+        input.pointer.any_click()
+            && input
+                .pointer
+                .interact_pos()
+                .is_some_and(|pos| !area.response.rect.contains(pos) && !button.rect.contains(pos))
+    };
+    if clicked_outside || ui.input().key_pressed(Key::Escape) {
+        ui.memory().close_popup();
+    }
+    Some(area.inner)
+}
+
+/// the text of the search field and the option which is chosen with the arrow keys
+#[derive(Clone, Default)]
+struct SearchState {
+    search: String,
+    cursor: usize,
+}
+
+/// shows a search field and the options which match it, the usefull ones first
+/// an option can be chosen with the arrow keys and enter
+/// returns the index of the option which was clicked or chosen
+pub(crate) fn show_searchable_options(
+    ui: &mut Ui,
+    id: Id,
+    options: &[SearchOption],
+    checkboxes: bool,
+) -> Option<usize> {
+    let mut state = ui.data().get_temp_mut_or_default::<SearchState>(id).clone();
+
+    let response = ui.add(TextEdit::singleline(&mut state.search).hint_text("search"));
+    //the search field gets the focus when the dropdown is opened
+    if ui.memory().focus().is_none() {
+        response.request_focus();
+    }
+
+    let mut matches: Vec<(usize, i32, Vec<usize>)> = options
+        .iter()
+        .enumerate()
+        .filter_map(|(i, option)| {
+            fuzzy_match(&state.search, &option.name).map(|(score, matched)| (i, score, matched))
+        })
+        .collect();
+    //the sort is stable so options with the same score keep their order
+    matches.sort_by_key(|(i, score, _matched)| (!options[*i].usefull, -score));
+
+    if response.changed() {
+        state.cursor = 0;
+    }
+    let (down, up, enter) = {
+        let input = ui.input();
+        (
+            input.key_pressed(Key::ArrowDown),
+            input.key_pressed(Key::ArrowUp),
+            input.key_pressed(Key::Enter),
+        )
+    };
+    if down {
+        state.cursor = (state.cursor + 1).min(matches.len().saturating_sub(1));
+    }
+    if up {
+        state.cursor = state.cursor.saturating_sub(1);
+    }
+    //a single line text field loses the focus when enter is pressed
+    let keyboard_active = response.has_focus() || response.lost_focus();
+
+    let mut chosen = None;
+    for (position, (i, _score, matched)) in matches.iter().enumerate() {
+        let option = &options[*i];
+        let text = highlighted_text(ui, option, matched);
+        let option_response = if checkboxes {
+            let mut checked = option.selected;
+            ui.checkbox(&mut checked, text)
+        } else {
+            ui.selectable_label(option.selected, text)
+        };
+
+        if keyboard_active && position == state.cursor {
+            ui.painter()
+                .rect_stroke(option_response.rect, 2.0, ui.visuals().selection.stroke);
+            if down || up {
+                option_response.scroll_to_me(None);
+            }
+        }
+        if option_response.clicked() {
+            chosen = Some(*i);
+        }
+    }
+    if matches.is_empty() {
+        ui.label(RichText::new("no matching label").color(Color32::DARK_GRAY));
+    }
+    if enter && response.lost_focus() {
+        chosen = chosen.or_else(|| matches.get(state.cursor).map(|(i, _score, _matched)| *i));
+    }
+
+    ui.data().insert_temp(id, state);
+    chosen
+}
+
+/// the text of the option with the matched characters highlighted
+fn highlighted_text(ui: &Ui, option: &SearchOption, matched: &[usize]) -> LayoutJob {
+    let font_id = TextStyle::Button.resolve(ui.style());
+    let color = if option.usefull {
+        ui.visuals().text_color()
+    } else {
+        Color32::DARK_GRAY
+    };
+    let normal = TextFormat::simple(font_id.clone(), color);
+    let highlight = TextFormat::simple(font_id, Color32::GOLD);
+
+    let mut job = LayoutJob::default();
+    let mut buffer = [0; 4];
+    for (i, c) in option.name.chars().enumerate() {
+        let format = if matched.contains(&i) {
+            highlight.clone()
+        } else {
+            normal.clone()
+        };
+        job.append(c.encode_utf8(&mut buffer), 0.0, format);
+    }
+    job.append(&format!(" ({})", group_digits(option.count)), 0.0, normal);
+    job
+}
+
+/// matches the characters of search in the same order in text, ignoring the case
+/// returns a score which is higher for consecutive matches and matches at the start of words
+/// together with the indices of the matched characters of text
+fn fuzzy_match(search: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let mut search = search.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut matched = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in text.chars().enumerate() {
+        let next = match search.peek() {
+            Some(&next) => next,
+            None => break,
+        };
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            score += 1;
+            if matched.last().map(|&last| last + 1) == Some(i) {
+                score += 4;
+            }
+            if previous
+                .map(|previous| !previous.is_alphanumeric())
+                .unwrap_or(true)
+            {
+                score += 3;
+            }
+            matched.push(i);
+            search.next();
+        }
+        previous = Some(c);
+    }
+
+    search.peek().is_none().then_some((score, matched))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_match;
+
+    #[test]
+    fn empty_search_matches_everything() {
+        assert_eq!(fuzzy_match("", "Rain"), Some((0, Vec::new())));
+        assert_eq!(fuzzy_match("", ""), Some((0, Vec::new())));
+        assert_eq!(fuzzy_match("r", ""), None);
+    }
+
+    #[test]
+    fn indices_are_those_of_chars() {
+        //ß takes two bytes, so the byte index of ü would be 9
+        let (_score, matched) = fuzzy_match("sü", "Straße Süd").unwrap();
+        assert_eq!(matched, vec![0, 8]);
+        let text: Vec<char> = "Straße Süd".chars().collect();
+        assert_eq!(text[8], 'ü');
+
+        let (_score, matched) = fuzzy_match("üd", "Straße Süd").unwrap();
+        assert_eq!(matched, vec![8, 9]);
+
+        //the case is ignored for other alphabets too
+        assert_eq!(fuzzy_match("über", "ÜBER").unwrap().1, vec![0, 1, 2, 3]);
+        assert_eq!(fuzzy_match("ß", "Strasse"), None);
+    }
+
+    #[test]
+    fn prefixes_rank_above_scattered_matches() {
+        let score = |search, text| fuzzy_match(search, text).unwrap().0;
+        assert!(score("sun", "Sunny") > score("sun", "Snow unknown"));
+        assert!(score("sun", "Sunny") > score("sun", "Snow and ruin"));
+        //a match at the start of a word is better than one within a word
+        assert!(score("city", "Road:City") > score("city", "Electricity"));
+        assert_eq!(fuzzy_match("rain", "Sun"), None);
+    }
+}
@@ -7,6 +7,12 @@ where
     SL: SubLabel,
 {
     fn get_labels(&self) -> &[L];
+
+    /// a number which changes whenever the labels change, so the filter can notice edited data
+    /// without it edits have to be announced with [`FilterEngine::data_changed`](crate::FilterEngine::data_changed)
+    fn version(&self) -> u64 {
+        0
+    }
 }
 
 impl<'s, L, TL, SL> LabeledData<L, TL, SL> for &'s [L]
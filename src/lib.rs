@@ -4,13 +4,29 @@
 //#![allow(unreachable_code)]
 //#![allow(dead_code)]
 
-#![feature(drain_filter)]
-
+mod co_occurrence;
+pub use co_occurrence::{CoOccurrence, CoOccurrenceScore};
+mod explanation;
+pub use explanation::{Explanation, FilterChange, Fix, Rejection, RejectionReason};
+mod filter_engine;
+pub use filter_engine::{BitSet, FilterConfig, FilterEngine, FunnelStep, WorkerPool};
+mod filter_group;
+mod filter_state;
+pub use filter_state::{FilterPreset, MainFilterState};
+#[cfg(feature = "egui")]
 mod main_filter;
+#[cfg(feature = "egui")]
 pub use main_filter::MainFilter;
+pub mod query;
+pub use query::QueryError;
 mod sub_filter;
+pub use sub_filter::LabelOptions;
 pub(crate) use sub_filter::SubFilter;
 mod label;
+#[cfg(feature = "egui")]
+mod label_search;
 pub use label::{Label, SubLabel, TopLabel};
+mod label_distribution;
+pub use label_distribution::LabelDistribution;
 mod labeled_data;
 pub use labeled_data::LabeledData;
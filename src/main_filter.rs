@@ -1,12 +1,26 @@
-use std::sync::Arc;
+use std::hash::Hash;
 
-use eframe::egui::{Button, Grid, ProgressBar, Ui};
+use eframe::{
+    egui::{
+        Button, CollapsingHeader, ComboBox, Grid, Id, Key, Modifiers, ProgressBar, Response,
+        RichText, ScrollArea, TextEdit, TextStyle, Ui,
+    },
+    epaint::{
+        text::{LayoutJob, TextFormat},
+        Color32, Stroke,
+    },
+};
 
-use crate::{sub_filter::FilterInfo, Label, LabeledData, SubFilter, SubLabel, TopLabel};
+use crate::{
+    co_occurrence::show_score_picker,
+    filter_group::{FilterGroup, FilterNode, GroupAction, GroupInfo, GroupOperator},
+    sub_filter::{text_with_count, Matching, SubFilterCore},
+    CoOccurrenceScore, Explanation, FilterConfig, FilterEngine, FilterPreset, FunnelStep, Label,
+    LabeledData, MainFilterState, QueryError, SubFilter, SubLabel, TopLabel,
+};
 
-use self::filter_calculation::ThreadCommunicator;
-
-mod filter_calculation;
+/// the maximal number of states which are kept for undo and redo
+const HISTORY_LENGTH: usize = 100;
 
 pub struct MainFilter<L, TL, SL>
 where
@@ -14,11 +28,35 @@ where
     TL: TopLabel,
     SL: SubLabel,
 {
-    filters: Vec<SubFilter<L, TL, SL>>,
-    top_level_label_options: Vec<L>,
+    engine: FilterEngine<L, TL, SL>,
     default_label: Option<L>,
+    presets: Vec<FilterPreset<L, TL, SL>>,
+    /// the index of the preset which was applied or saved last
+    selected_preset: Option<usize>,
+    /// the text field for the name of the preset
+    preset_name: String,
+    /// the text field for the query
+    query: String,
+    query_error: Option<QueryError>,
+    /// the states of the filters after every change, the oldest first
+    history: Vec<MainFilterState<L, TL, SL>>,
+    /// the index of the current state in history
+    history_position: usize,
     needs_init: bool,
-    thread_communicator: Arc<ThreadCommunicator<L, TL, SL>>,
+    /// if the data was changed in place since the last call of show
+    data_changed: bool,
+    /// if the data is checked for changes whenever it is passed to show
+    detect_changes: bool,
+    /// if the background threads can request a repaint of the ui
+    has_repaint_callback: bool,
+    /// the ids of the widgets are derived from it, so several filters can be shown in the same ui
+    id_source: Id,
+    /// if the histogram of the labels of the filtered data is shown under the filters
+    show_label_distribution: bool,
+    /// the top level label whose sub level labels are shown in the histogram
+    distribution_drill_down: Option<L>,
+    /// how the cells of the co-occurrence heat map are colored
+    co_occurrence_score: CoOccurrenceScore,
 }
 
 impl<L, TL, SL> MainFilter<L, TL, SL>
@@ -28,131 +66,689 @@ where
     SL: SubLabel,
 {
     pub fn new() -> MainFilter<L, TL, SL> {
+        Self::with_config(FilterConfig::default())
+    }
+
+    /// e.g. `MainFilter::with_config(FilterConfig::default().with_threads(16))`
+    /// or `MainFilter::with_config(FilterConfig::default().with_pool(&pool))` to share the threads
+    pub fn with_config(config: FilterConfig) -> MainFilter<L, TL, SL> {
+        let detect_changes = config.detect_changes;
         MainFilter {
-            filters: Vec::new(),
-            top_level_label_options: Vec::new(),
+            engine: FilterEngine::with_config(config),
             default_label: None,
+            presets: Vec::new(),
+            selected_preset: None,
+            preset_name: String::new(),
+            query: String::new(),
+            query_error: None,
+            history: vec![MainFilterState {
+                filters: FilterGroup::new(0, GroupOperator::And),
+            }],
+            history_position: 0,
 
             needs_init: true,
-            thread_communicator: ThreadCommunicator::new(),
+            data_changed: false,
+            detect_changes,
+            has_repaint_callback: false,
+            id_source: Id::new("label_filter_lib"),
+            show_label_distribution: false,
+            distribution_drill_down: None,
+            co_occurrence_score: CoOccurrenceScore::Lift,
         }
     }
 
-    pub fn get_filter_map<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> Vec<usize> {
-        data.iter()
+    /// several filters which are shown in the same ui need different id sources
+    /// e.g. `MainFilter::new().with_id_source("left_plot")`
+    pub fn with_id_source(mut self, id_source: impl Hash) -> Self {
+        self.id_source = Id::new(id_source);
+        self
+    }
+
+    /// shows a histogram of the labels of the data which passes the filters under the filters,
+    /// clicking a bar adds a filter for the label
+    pub fn with_label_distribution(mut self, show: bool) -> Self {
+        self.show_label_distribution = show;
+        self
+    }
+
+    /// restores the filters of state and starts the calculation of the label options for data
+    pub fn from_state<D: LabeledData<L, TL, SL>>(
+        state: MainFilterState<L, TL, SL>,
+        data: &[D],
+    ) -> MainFilter<L, TL, SL> {
+        Self::from_state_with_config(state, FilterConfig::default(), data)
+    }
+
+    /// like [`from_state`](Self::from_state) but the engine is configured like with [`with_config`](Self::with_config)
+    /// e.g. to share the threads of a pool
+    pub fn from_state_with_config<D: LabeledData<L, TL, SL>>(
+        state: MainFilterState<L, TL, SL>,
+        config: FilterConfig,
+        data: &[D],
+    ) -> MainFilter<L, TL, SL> {
+        let mut main_filter = Self::with_config(config);
+        main_filter.set_state(&state, data);
+        main_filter.history = vec![main_filter.state()];
+        main_filter.needs_init = false;
+        main_filter
+    }
+
+    /// replaces all filters by the filters of state
+    fn set_state<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        state: &MainFilterState<L, TL, SL>,
+        data: &[D],
+    ) {
+        self.engine.set_state(state, data);
+        self.sync_query();
+    }
+
+    /// the engine which calculates the filter map and the label options
+    pub fn engine(&self) -> &FilterEngine<L, TL, SL> {
+        &self.engine
+    }
+
+    pub fn presets(&self) -> &[FilterPreset<L, TL, SL>] {
+        &self.presets
+    }
+
+    /// returns a copy of all presets e.g. to share them with other users
+    pub fn export_presets(&self) -> Vec<FilterPreset<L, TL, SL>> {
+        self.presets.clone()
+    }
+
+    /// adds the presets, existing presets with the same name are replaced
+    pub fn import_presets(&mut self, presets: impl IntoIterator<Item = FilterPreset<L, TL, SL>>) {
+        for preset in presets {
+            self.insert_preset(preset);
+        }
+    }
+
+    /// saves the current filters as preset with the given name
+    /// an existing preset with the same name is replaced
+    pub fn save_preset(&mut self, name: impl Into<String>) {
+        let preset = FilterPreset {
+            name: name.into(),
+            state: self.state(),
+        };
+        self.selected_preset = Some(self.insert_preset(preset));
+    }
+
+    /// returns the index of the inserted preset
+    fn insert_preset(&mut self, preset: FilterPreset<L, TL, SL>) -> usize {
+        if let Some(index) = self.presets.iter().position(|p| p.name == preset.name) {
+            self.presets[index] = preset;
+            index
+        } else {
+            self.presets.push(preset);
+            self.presets.len() - 1
+        }
+    }
+
+    /// the names of the presets stay unique so a name which another preset has is rejected
+    /// returns if the preset was renamed
+    pub fn rename_preset(&mut self, index: usize, name: impl Into<String>) -> bool {
+        let name = name.into();
+        if self.is_preset_name_taken(index, &name) {
+            return false;
+        }
+        match self.presets.get_mut(index) {
+            Some(preset) => {
+                preset.name = name;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// if a preset other than the preset at index has the name
+    fn is_preset_name_taken(&self, index: usize, name: &str) -> bool {
+        self.presets
+            .iter()
             .enumerate()
-            .filter_map(|(i, data)| {
-                for filter in &self.filters {
-                    if !filter.filter(data) {
-                        return None;
-                    }
+            .any(|(i, preset)| i != index && preset.name == name)
+    }
+
+    pub fn delete_preset(&mut self, index: usize) {
+        if index < self.presets.len() {
+            self.presets.remove(index);
+            self.selected_preset = match self.selected_preset {
+                Some(selected) if selected == index => None,
+                Some(selected) if selected > index => Some(selected - 1),
+                selected => selected,
+            };
+        }
+    }
+
+    /// replaces all filters by the filters of the preset
+    pub fn apply_preset<D: LabeledData<L, TL, SL>>(&mut self, index: usize, data: &[D]) {
+        if let Some(preset) = self.presets.get(index) {
+            let state = preset.state.clone();
+            self.set_state(&state, data);
+            self.record_history();
+            self.selected_preset = Some(index);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history_position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history_position + 1 < self.history.len()
+    }
+
+    /// restores the filters before the last change
+    /// returns if there was a change to undo
+    pub fn undo<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        self.history_position -= 1;
+        let state = self.history[self.history_position].clone();
+        self.set_state(&state, data);
+        true
+    }
+
+    /// restores the filters before the last undo
+    /// returns if there was an undo to redo
+    pub fn redo<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+        self.history_position += 1;
+        let state = self.history[self.history_position].clone();
+        self.set_state(&state, data);
+        true
+    }
+
+    /// stores the current filters as newest state, the undone states are dropped
+    fn record_history(&mut self) {
+        self.history.truncate(self.history_position + 1);
+        self.history.push(self.state());
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+        self.history_position = self.history.len() - 1;
+        self.sync_query();
+    }
+
+    /// prints the filters into the text field for the query
+    /// every change of the filters goes through here or set_state, so enter never applies an old query
+    fn sync_query(&mut self) {
+        self.query = self.query();
+        self.query_error = None;
+    }
+
+    /// returns the configuration of all filters e.g. to store it
+    pub fn state(&self) -> MainFilterState<L, TL, SL> {
+        self.engine.state()
+    }
+
+    /// announces that the labels of the data were changed in place
+    /// the label options are calculated again the next time the data is passed to show
+    pub fn data_changed(&mut self) {
+        self.data_changed = true;
+    }
+
+    /// calculates the label options again if data is not the data of the last calculation
+    /// see [`FilterEngine::set_data`]
+    pub fn set_data<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        self.engine.set_data(data);
+    }
+
+    /// calculates the label options again after items were appended to the data
+    /// see [`FilterEngine::data_appended`]
+    pub fn data_appended<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        self.engine.data_appended(data);
+    }
+
+    /// returns why the item with the index passes or does not pass the filters
+    /// or None if data has no item with the index
+    pub fn explain<D: LabeledData<L, TL, SL>>(
+        &self,
+        data: &[D],
+        index: usize,
+    ) -> Option<Explanation> {
+        data.get(index).map(|item| self.engine.explain(item))
+    }
+
+    /// shows a small label, e.g. next to a row of the data, whose tooltip explains the result of the filters
+    /// nothing is shown if data has no item with the index
+    pub fn show_explanation<D: LabeledData<L, TL, SL>>(
+        &self,
+        ui: &mut Ui,
+        data: &[D],
+        index: usize,
+    ) -> Option<Response> {
+        let explanation = self.explain(data, index)?;
+        let text = if explanation.passes {
+            RichText::new("✔")
+        } else {
+            RichText::new("✖").color(Color32::RED)
+        };
+        Some(ui.label(text).on_hover_ui(|ui| explanation.show(ui)))
+    }
+
+    /// shows a heat map of which labels appear together in the data which passes the filters,
+    /// clicking a cell adds filters for both labels, see [`FilterEngine::co_occurrence`]
+    /// the threads count it from the first call on until it is disabled with [`set_co_occurrence`](Self::set_co_occurrence)
+    /// returns if the filters were changed
+    pub fn show_co_occurrence<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        ui: &mut Ui,
+        data: &[D],
+    ) -> bool {
+        ui.push_id(self.id_source, |ui| {
+            self.show_co_occurrence_scoped(ui, data)
+        })
+        .inner
+    }
+
+    /// enables or disables the counting of the co-occurrence by the threads,
+    /// e.g. to disable it when the heat map is hidden because it makes every calculation slower
+    pub fn set_co_occurrence<D: LabeledData<L, TL, SL>>(&mut self, enabled: bool, data: &[D]) {
+        self.engine.set_co_occurrence(enabled, data);
+    }
+
+    fn show_co_occurrence_scoped<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        ui: &mut Ui,
+        data: &[D],
+    ) -> bool {
+        //the matrix is counted with the label options so it is not counted in the ui thread
+        self.engine.set_co_occurrence(true, data);
+        let co_occurrence = match self.engine.co_occurrence() {
+            Some(co_occurrence) => co_occurrence,
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("counting which labels appear together");
+                });
+                return false;
+            }
+        };
+
+        show_score_picker(ui, &mut self.co_occurrence_score);
+        let score = self.co_occurrence_score;
+        let clicked = ScrollArea::both()
+            .show(ui, |ui| co_occurrence.show(ui, score))
+            .inner
+            .map(|(i, j)| [i, j].map(|k| co_occurrence.labels[k].0.clone()));
+
+        match clicked {
+            Some(labels) => {
+                for label in labels {
+                    let id = self.engine.free_id();
+                    self.engine
+                        .filters
+                        .push(FilterNode::Filter(SubFilter::new(label, id)));
                 }
-                Some(i)
-            })
-            .collect()
+                self.update_all_filter(data);
+                self.record_history();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// returns for every filter how much data it removes, see [`FilterEngine::funnel`]
+    pub fn funnel(&self) -> &[FunnelStep] {
+        self.engine.funnel()
+    }
+
+    /// see the [`engine`](Self::engine) for variants which do not allocate a Vec,
+    /// e.g. [`FilterEngine::filter_indices`] or [`FilterEngine::filter_count`]
+    pub fn get_filter_map<D: LabeledData<L, TL, SL>>(&self, data: &[D]) -> Vec<usize> {
+        self.engine.filter_map(data)
     }
 
     /// returns if the filter_map could have changed
     pub fn show<D: LabeledData<L, TL, SL>>(&mut self, ui: &mut Ui, data: &[D]) -> bool {
+        //the ids of all widgets are derived from the id source
+        ui.push_id(self.id_source, |ui| self.show_scoped(ui, data))
+            .inner
+    }
+
+    fn show_scoped<D: LabeledData<L, TL, SL>>(&mut self, ui: &mut Ui, data: &[D]) -> bool {
+        //the filter map changes with the data
+        let mut data_was_changed = false;
         if self.needs_init {
             self.update_all_filter(data);
             self.needs_init = false;
+        } else if self.data_changed {
+            self.engine.data_changed(data);
+            data_was_changed = true;
+        } else if self.detect_changes {
+            data_was_changed = self.engine.set_data(data);
         }
-        if let Some((filters, top_level_label_options)) =
-            self.thread_communicator.try_get_finished()
-        {
-            self.filters = filters;
-            if self.default_label.is_none() {
-                self.default_label = top_level_label_options.first().cloned();
-            }
-            self.top_level_label_options = top_level_label_options;
+        self.data_changed = false;
+        if !self.has_repaint_callback {
+            //the results of the background threads are shown without waiting for user input
+            let ctx = ui.ctx().clone();
+            self.engine
+                .set_repaint_callback(move || ctx.request_repaint());
+            self.has_repaint_callback = true;
+        }
+        if self.engine.try_update() && self.default_label.is_none() {
+            self.default_label = self
+                .engine
+                .top_level_label_options()
+                .first()
+                .map(|(label, _count)| label.clone());
         }
 
         let mut filter_was_changed = false;
-        let mut new_filter = None;
+        let mut actions = Vec::new();
+        let mut apply_preset = None;
+        let mut undo = false;
+        let mut redo = false;
 
         ui.horizontal(|ui| {
-            let button = if self.top_level_label_options.is_empty() {
+            let top_level_label_options = self.engine.top_level_label_options();
+            let button = if top_level_label_options.is_empty() {
                 Button::new("Add Pointless Filter")
             } else {
                 Button::new("Add Filter")
             };
 
-            let clicked = ui
-                .add_enabled(self.default_label.is_some(), button)
-                .clicked();
+            let mut response = ui.add_enabled(self.default_label.is_some(), button);
+            if let Some((label, count)) = top_level_label_options.last() {
+                response = response.on_hover_text(format!(
+                    "adds {}",
+                    text_with_count(label.get_top_level_label(), *count)
+                ));
+            }
+            let clicked = response.clicked();
 
             if clicked {
-                filter_was_changed = true;
-                let id = (0..)
-                    .find(|&id| {
-                        for filter in self.filters.iter() {
-                            if filter.id() == id {
-                                return false;
-                            }
-                        }
-                        true
-                    })
-                    .expect("not to have more than i32 many filterns");
+                actions.push(GroupAction::AddFilter {
+                    group: self.engine.filters.id(),
+                });
+            }
 
-                let label = self
-                    .top_level_label_options
-                    .pop()
-                    .or_else(|| self.default_label.clone());
+            undo = ui
+                .add_enabled(self.can_undo(), Button::new("undo"))
+                .on_hover_text("Ctrl+Z")
+                .clicked();
+            redo = ui
+                .add_enabled(self.can_redo(), Button::new("redo"))
+                .on_hover_text("Ctrl+Shift+Z")
+                .clicked();
 
-                new_filter = label.map(|label| SubFilter::new(label, id));
-            }
+            apply_preset = self.show_presets(ui);
 
             //if !self.thread_communicator.is_idle() {
-            let (a, b) = self.thread_communicator.get_progress();
+            let (a, b) = self.engine.progress();
             let progress = a as f32 / b as f32;
             let progress_bar = ProgressBar::new(progress).animate(true);
             ui.add(progress_bar);
             //}
         });
 
-        Grid::new("label_filter_lib").show(ui, |ui| {
-            self.filters.drain_filter(|filter| {
-                let FilterInfo {
-                    needs_removal,
-                    was_changed,
-                } = filter.show(ui);
-                filter_was_changed |= was_changed;
-                ui.end_row();
-                needs_removal
-            });
+        let query_applied = self.show_query(ui, data);
+
+        let all_groups = self.engine.filters.groups();
+        Grid::new("filters").show(ui, |ui| {
+            let GroupInfo { was_changed, .. } =
+                self.engine.filters.show(ui, 0, &all_groups, &mut actions);
+            filter_was_changed |= was_changed;
         });
 
-        if let Some(new_filter) = new_filter {
-            assert!(filter_was_changed);
-            self.filters.push(new_filter);
+        if self.show_label_distribution {
+            let clicked = CollapsingHeader::new("label distribution")
+                .show(ui, |ui| {
+                    self.engine
+                        .label_distribution()
+                        .show(ui, &mut self.distribution_drill_down)
+                })
+                .body_returned
+                .flatten();
+            if let Some((label, matching)) = clicked {
+                let id = self.engine.free_id();
+                let core = SubFilterCore::new(id, label, matching, false, true);
+                self.engine
+                    .filters
+                    .push(FilterNode::Filter(SubFilter::from(core)));
+                filter_was_changed = true;
+            }
+        }
+
+        for action in actions {
+            filter_was_changed |= self.apply_action(action);
         }
 
         if filter_was_changed {
             self.update_all_filter(data);
+            self.record_history();
+        }
+
+        if let Some(index) = apply_preset {
+            self.apply_preset(index, data);
+            filter_was_changed = true;
         }
 
-        filter_was_changed
+        //the shortcuts are left to the text fields while they have the focus
+        if ui.memory().focus().is_none() {
+            let mut input = ui.input_mut();
+            redo |= input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+            undo |= input.consume_key(Modifiers::COMMAND, Key::Z);
+        }
+        if undo {
+            filter_was_changed |= self.undo(data);
+        } else if redo {
+            filter_was_changed |= self.redo(data);
+        }
+        filter_was_changed |= query_applied;
+
+        filter_was_changed || data_was_changed
     }
 
     pub fn add_filter<D: LabeledData<L, TL, SL>>(&mut self, label: L, data: &[D]) {
-        let id = (0..)
-            .find(|&id| {
-                for filter in self.filters.iter() {
-                    if filter.id() == id {
-                        return false;
+        self.engine.add_filter(label, data);
+        self.record_history();
+    }
+
+    /// moves a filter within its group, see [`FilterEngine::move_filter`]
+    pub fn move_filter<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        from: usize,
+        to: usize,
+        data: &[D],
+    ) -> bool {
+        let moved = self.engine.move_filter(from, to, data);
+        if moved {
+            self.record_history();
+        }
+        moved
+    }
+
+    /// replaces all filters by the filters of the query
+    /// see [`query`](crate::query) for the syntax, the names are resolved to the labels of data
+    pub fn set_query<D: LabeledData<L, TL, SL>>(
+        &mut self,
+        query: &str,
+        data: &[D],
+    ) -> Result<(), QueryError> {
+        self.engine.set_query(query, data)?;
+        self.record_history();
+        Ok(())
+    }
+
+    /// returns the filters in the syntax of [`query`](crate::query)
+    pub fn query(&self) -> String {
+        self.engine.query()
+    }
+
+    /// shows the text field for the query, the query is applied if enter is pressed
+    /// returns if the query was applied
+    fn show_query<D: LabeledData<L, TL, SL>>(&mut self, ui: &mut Ui, data: &[D]) -> bool {
+        let error_span = self.query_error.as_ref().map(|error| error.span());
+        let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+            let font_id = TextStyle::Monospace.resolve(ui.style());
+            let color = ui.visuals().text_color();
+            let normal = TextFormat::simple(font_id.clone(), color);
+            let error = TextFormat {
+                underline: Stroke::new(2.0, Color32::RED),
+                color: Color32::RED,
+                ..TextFormat::simple(font_id, color)
+            };
+
+            let mut job = LayoutJob::default();
+            match error_span.clone() {
+                Some(span) if span.end <= text.len() && text.is_char_boundary(span.start) => {
+                    job.append(&text[..span.start], 0.0, normal.clone());
+                    //an error at the end of the query gets a visible marker
+                    let error_text = if span.is_empty() {
+                        " "
+                    } else {
+                        &text[span.clone()]
+                    };
+                    job.append(error_text, 0.0, error);
+                    job.append(&text[span.end..], 0.0, normal);
+                }
+                _ => job.append(text, 0.0, normal),
+            }
+            job.wrap.max_width = wrap_width;
+            ui.fonts().layout_job(job)
+        };
+
+        let response = ui.add(
+            TextEdit::singleline(&mut self.query)
+                .hint_text("Weather:Rain AND NOT Road:Highway")
+                .desired_width(f32::INFINITY)
+                .layouter(&mut layouter),
+        );
+        if response.changed() {
+            self.query_error = None;
+        }
+
+        let mut applied = false;
+        if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+            let query = self.query.clone();
+            match self.set_query(&query, data) {
+                Ok(()) => applied = true,
+                Err(error) => self.query_error = Some(error),
+            }
+        }
+
+        if let Some(error) = &self.query_error {
+            ui.colored_label(Color32::RED, error.to_string());
+        }
+
+        applied
+    }
+
+    /// shows the preset picker and the buttons to manage the presets
+    /// returns the index of the preset which should be applied
+    fn show_presets(&mut self, ui: &mut Ui) -> Option<usize> {
+        let mut apply_preset = None;
+
+        let selected_text = self
+            .selected_preset
+            .and_then(|index| self.presets.get(index))
+            .map(|preset| preset.name.as_str())
+            .unwrap_or("Presets");
+        ComboBox::from_id_source("label_filter_presets")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for (index, preset) in self.presets.iter().enumerate() {
+                    let selected = self.selected_preset == Some(index);
+                    if ui.selectable_label(selected, &preset.name).clicked() {
+                        apply_preset = Some(index);
                     }
                 }
-                true
-            })
-            .expect("not to have more than i32 many filterns");
+            });
+
+        ui.add(TextEdit::singleline(&mut self.preset_name).desired_width(80.0))
+            .on_hover_text("name of the preset");
+
+        let name_is_empty = self.preset_name.trim().is_empty();
+        if ui
+            .add_enabled(!name_is_empty, Button::new("save"))
+            .on_hover_text("saves the filters as preset")
+            .clicked()
+        {
+            self.save_preset(self.preset_name.trim().to_string());
+        }
+
+        if let Some(index) = self.selected_preset {
+            let name_is_taken = self.is_preset_name_taken(index, self.preset_name.trim());
+            if ui
+                .add_enabled(!name_is_empty && !name_is_taken, Button::new("rename"))
+                .on_disabled_hover_text("needs a name which no other preset has")
+                .clicked()
+            {
+                self.rename_preset(index, self.preset_name.trim().to_string());
+            }
+            if ui.button("delete").clicked() {
+                self.delete_preset(index);
+            }
+        }
+
+        apply_preset
+    }
 
-        self.filters.push(SubFilter::new(label, id));
-        self.update_all_filter(data);
+    /// returns if the filters were changed
+    fn apply_action(&mut self, action: GroupAction) -> bool {
+        match action {
+            GroupAction::AddFilter { group } => {
+                let id = self.engine.free_id();
+                let label = self
+                    .engine
+                    .top_level_label_options
+                    .pop()
+                    .map(|(label, _count)| label)
+                    .or_else(|| self.default_label.clone());
+
+                match (label, self.engine.filters.group_mut(group)) {
+                    (Some(label), Some(group)) => {
+                        //like a top level option the new filter keeps any label of its top level label
+                        let core =
+                            SubFilterCore::new(id, label, Matching::AnySubLabel, false, true);
+                        group.push(FilterNode::Filter(SubFilter::from(core)));
+                        true
+                    }
+                    _ => false,
+                }
+            }
+
+            GroupAction::AddGroup { group } => {
+                let id = self.engine.free_id();
+                if let Some(group) = self.engine.filters.group_mut(group) {
+                    group.push(FilterNode::Group(FilterGroup::new(id, GroupOperator::Or)));
+                    true
+                } else {
+                    false
+                }
+            }
+
+            GroupAction::MoveFilter { filter, group } => {
+                let filters = &mut self.engine.filters;
+                if filters.group_mut(group).is_none() {
+                    return false;
+                }
+                if let Some(filter) = filters.take_filter(&mut |f| f.id() == filter) {
+                    filters
+                        .group_mut(group)
+                        .expect("group to exist")
+                        .push(FilterNode::Filter(filter));
+                    true
+                } else {
+                    false
+                }
+            }
+
+            GroupAction::DropFilter {
+                filter,
+                target,
+                after,
+            } => self.engine.move_filter_next_to(filter, target, after),
+        }
     }
 
-    fn update_all_filter<D: LabeledData<L, TL, SL>>(&self, data: &[D]) {
-        self.thread_communicator.start(data, &self.filters);
+    fn update_all_filter<D: LabeledData<L, TL, SL>>(&mut self, data: &[D]) {
+        self.engine.calculate(data);
     }
 }
 
@@ -0,0 +1,813 @@
+//! a textual syntax for the filters of a [`FilterEngine`](crate::FilterEngine)
+//!
+//! ```text
+//! Weather:Rain AND NOT Road:Highway
+//! (Weather:{Rain, Snow} OR Daytime:Night) AND Road:*
+//! ```
+//!
+//! * `Top:Sub` matches the label, a label without sub level label is just written as `Top`
+//! * `Top:*` matches every label with the top level label
+//! * `Top:{A, B}` matches data with one of the labels, `Top:ALL{A, B}` data with all of them
+//! * `NOT` in front of a filter inverts it, in front of parentheses it creates a group where none may match
+//! * `AND` binds stronger than `OR`, parentheses create a group
+//! * `#` in front of a filter deactivates it
+//!
+//! names are resolved through the `Display` impls of the labels,
+//! names with whitespace or special characters have to be quoted: `"Heavy Rain"`
+//!
+//! a printed query is parsed to filters with the same meaning but not always with the same groups:
+//! the ids are new, a group with one child comes back as and group
+//! and a not group as root comes back as the only child of an and group
+
+use std::{error::Error, fmt::Display, marker::PhantomData, ops::Range};
+
+use crate::{
+    filter_group::{FilterGroup, FilterNode, GroupOperator},
+    sub_filter::{Matching, SubFilterCore},
+    Label, SubLabel, TopLabel,
+};
+
+/// the characters which can not be part of an unquoted name
+const SPECIAL_CHARACTERS: &[char] = &['(', ')', '{', '}', ':', ',', '*', '#', '"'];
+const KEYWORDS: &[&str] = &["AND", "OR", "NOT", "ALL"];
+
+/// an error of a query together with the byte range of the query where it occurred
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    message: String,
+    span: Range<usize>,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// the byte range of the query which caused the error
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Comma,
+    Star,
+    Hash,
+    And,
+    Or,
+    Not,
+    Name { text: String, quoted: bool },
+    End,
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::LeftParen => write!(f, "'('"),
+            TokenKind::RightParen => write!(f, "')'"),
+            TokenKind::LeftBrace => write!(f, "'{{'"),
+            TokenKind::RightBrace => write!(f, "'}}'"),
+            TokenKind::Colon => write!(f, "':'"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Star => write!(f, "'*'"),
+            TokenKind::Hash => write!(f, "'#'"),
+            TokenKind::And => write!(f, "AND"),
+            TokenKind::Or => write!(f, "OR"),
+            TokenKind::Not => write!(f, "NOT"),
+            TokenKind::Name { text, .. } => write!(f, "'{}'", text),
+            TokenKind::End => write!(f, "the end of the query"),
+        }
+    }
+}
+
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ':' => TokenKind::Colon,
+            ',' => TokenKind::Comma,
+            '*' => TokenKind::Star,
+            '#' => TokenKind::Hash,
+            '"' => {
+                let mut text = String::new();
+                let mut closed = false;
+                while let Some((_i, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_i, escaped)) => text.push(escaped),
+                            None => break,
+                        },
+                        c => text.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(QueryError::new("unclosed quote", start..query.len()));
+                }
+                TokenKind::Name { text, quoted: true }
+            }
+            c => {
+                let mut text = c.to_string();
+                while let Some(&(_i, c)) = chars.peek() {
+                    if c.is_whitespace() || SPECIAL_CHARACTERS.contains(&c) {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                match text.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Name {
+                        text,
+                        quoted: false,
+                    },
+                }
+            }
+        };
+
+        let end = chars.peek().map(|&(i, _c)| i).unwrap_or(query.len());
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        span: query.len()..query.len(),
+    });
+    Ok(tokens)
+}
+
+/// the children of a group together with the operator which combines them
+/// the operator is And if there is no operator between the children
+struct Sequence<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    operator: GroupOperator,
+    nodes: Vec<FilterNode<SubFilterCore<L, TL, SL>>>,
+}
+
+struct Parser<'l, L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    tokens: Vec<Token>,
+    position: usize,
+    labels: Vec<&'l L>,
+    next_id: u32,
+
+    marker_0: PhantomData<TL>,
+    marker_1: PhantomData<SL>,
+}
+
+/// parses the query, the names are resolved to the given labels
+pub(crate) fn parse_query<'l, L, TL, SL>(
+    query: &str,
+    labels: impl IntoIterator<Item = &'l L>,
+) -> Result<FilterGroup<SubFilterCore<L, TL, SL>>, QueryError>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let mut labels: Vec<&L> = labels.into_iter().collect();
+    labels.sort();
+    labels.dedup();
+
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+        labels,
+        //the root group has the id 0
+        next_id: 1,
+        marker_0: PhantomData,
+        marker_1: PhantomData,
+    };
+
+    let Sequence { operator, nodes } = parser.parse_expression()?;
+    parser.expect(TokenKind::End)?;
+
+    let mut root = FilterGroup::new(0, operator);
+    for node in nodes {
+        root.push(node);
+    }
+    Ok(root)
+}
+
+impl<'l, L, TL, SL> Parser<'l, L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> &Token {
+        let token = &self.tokens[self.position];
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Range<usize>, QueryError> {
+        let token = self.next();
+        if token.kind == kind {
+            Ok(token.span.clone())
+        } else {
+            Err(QueryError::new(
+                format!("expected {} but found {}", kind, token.kind),
+                token.span.clone(),
+            ))
+        }
+    }
+
+    fn new_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn group(
+        &mut self,
+        operator: GroupOperator,
+        nodes: Vec<FilterNode<SubFilterCore<L, TL, SL>>>,
+    ) -> FilterNode<SubFilterCore<L, TL, SL>> {
+        let mut group = FilterGroup::new(self.new_id(), operator);
+        for node in nodes {
+            group.push(node);
+        }
+        FilterNode::Group(group)
+    }
+
+    /// expression := and_expression ("OR" and_expression)*
+    fn parse_expression(&mut self) -> Result<Sequence<L, TL, SL>, QueryError> {
+        //an empty expression is an empty group
+        if matches!(self.peek().kind, TokenKind::RightParen | TokenKind::End) {
+            return Ok(Sequence {
+                operator: GroupOperator::And,
+                nodes: Vec::new(),
+            });
+        }
+
+        let mut and_sequences = vec![self.parse_and_expression()?];
+        while self.peek().kind == TokenKind::Or {
+            self.next();
+            and_sequences.push(self.parse_and_expression()?);
+        }
+
+        if and_sequences.len() == 1 {
+            return Ok(and_sequences.pop().expect("one sequence"));
+        }
+
+        let mut nodes = Vec::with_capacity(and_sequences.len());
+        for mut sequence in and_sequences {
+            let node = if sequence.nodes.len() == 1 {
+                sequence.nodes.pop().expect("one node")
+            } else {
+                self.group(GroupOperator::And, sequence.nodes)
+            };
+            nodes.push(node);
+        }
+        Ok(Sequence {
+            operator: GroupOperator::Or,
+            nodes,
+        })
+    }
+
+    /// and_expression := unary ("AND" unary)*
+    fn parse_and_expression(&mut self) -> Result<Sequence<L, TL, SL>, QueryError> {
+        let mut nodes = vec![self.parse_unary()?];
+        while self.peek().kind == TokenKind::And {
+            self.next();
+            nodes.push(self.parse_unary()?);
+        }
+        Ok(Sequence {
+            operator: GroupOperator::And,
+            nodes,
+        })
+    }
+
+    /// unary := "NOT" unary | "(" expression ")" | filter
+    fn parse_unary(&mut self) -> Result<FilterNode<SubFilterCore<L, TL, SL>>, QueryError> {
+        match self.peek().kind {
+            TokenKind::Not => {
+                self.next();
+                if self.peek().kind == TokenKind::LeftParen {
+                    //NOT (a OR b) is a group where none of the children may match
+                    let Sequence { operator, nodes } = self.parse_parenthesized()?;
+                    let nodes = if operator == GroupOperator::And && nodes.len() > 1 {
+                        vec![self.group(GroupOperator::And, nodes)]
+                    } else {
+                        nodes
+                    };
+                    Ok(self.group(GroupOperator::Not, nodes))
+                } else {
+                    match self.parse_unary()? {
+                        FilterNode::Filter(filter) => {
+                            let inverted = !filter.inverted();
+                            Ok(FilterNode::Filter(SubFilterCore::new(
+                                filter.id(),
+                                filter.label().clone(),
+                                filter.matching().clone(),
+                                inverted,
+                                filter.active(),
+                            )))
+                        }
+                        group => Ok(self.group(GroupOperator::Not, vec![group])),
+                    }
+                }
+            }
+            TokenKind::LeftParen => {
+                let Sequence { operator, nodes } = self.parse_parenthesized()?;
+                Ok(self.group(operator, nodes))
+            }
+            _ => self.parse_filter(),
+        }
+    }
+
+    fn parse_parenthesized(&mut self) -> Result<Sequence<L, TL, SL>, QueryError> {
+        self.expect(TokenKind::LeftParen)?;
+        let sequence = self.parse_expression()?;
+        self.expect(TokenKind::RightParen)?;
+        Ok(sequence)
+    }
+
+    /// filter := ["#"] name [":" ("*" | ["ALL"] "{" [name ("," name)*] "}" | name)]
+    fn parse_filter(&mut self) -> Result<FilterNode<SubFilterCore<L, TL, SL>>, QueryError> {
+        let active = if self.peek().kind == TokenKind::Hash {
+            self.next();
+            false
+        } else {
+            true
+        };
+
+        let (top_level_name, top_level_span) = self.parse_name("a label")?;
+        let candidates: Vec<&L> = self
+            .labels
+            .iter()
+            .copied()
+            .filter(|label| label.get_top_level_label().to_string() == top_level_name)
+            .collect();
+        let representative = *candidates.first().ok_or_else(|| {
+            QueryError::new(
+                format!("unknown label '{}'", top_level_name),
+                top_level_span.clone(),
+            )
+        })?;
+
+        let (label, matching) = if self.peek().kind == TokenKind::Colon {
+            self.next();
+            match &self.peek().kind {
+                TokenKind::Star => {
+                    self.next();
+                    (representative.clone(), Matching::AnySubLabel)
+                }
+                TokenKind::Name {
+                    text,
+                    quoted: false,
+                } if text == "ALL"
+                    && self.tokens[self.position + 1].kind == TokenKind::LeftBrace =>
+                {
+                    self.next();
+                    self.parse_set(&candidates, true)?
+                }
+                TokenKind::LeftBrace => self.parse_set(&candidates, false)?,
+                _ => {
+                    let (sub_level_name, sub_level_span) = self.parse_name("a sub label")?;
+                    let label = find_sub_level_label(&candidates, &sub_level_name)
+                        .ok_or_else(|| unknown_sub_level_label(&sub_level_name, sub_level_span))?;
+                    (label.clone(), Matching::Exact)
+                }
+            }
+        } else {
+            let label = candidates
+                .iter()
+                .find(|label| label.get_sub_level_label().is_none())
+                .ok_or_else(|| {
+                    QueryError::new(
+                        format!("'{}' needs a sub label", top_level_name),
+                        top_level_span,
+                    )
+                })?;
+            ((*label).clone(), Matching::Exact)
+        };
+
+        Ok(FilterNode::Filter(SubFilterCore::new(
+            self.new_id(),
+            label,
+            matching,
+            false,
+            active,
+        )))
+    }
+
+    /// set := "{" [name ("," name)*] "}"
+    fn parse_set(&mut self, candidates: &[&L], all: bool) -> Result<(L, Matching<L>), QueryError> {
+        self.expect(TokenKind::LeftBrace)?;
+        let mut labels: Vec<L> = Vec::new();
+        if self.peek().kind != TokenKind::RightBrace {
+            loop {
+                let (sub_level_name, sub_level_span) = self.parse_name("a sub label")?;
+                let label = find_sub_level_label(candidates, &sub_level_name)
+                    .ok_or_else(|| unknown_sub_level_label(&sub_level_name, sub_level_span))?;
+                labels.push(label.clone());
+
+                if self.peek().kind == TokenKind::Comma {
+                    self.next();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenKind::RightBrace)?;
+
+        labels.sort();
+        labels.dedup();
+        let label = labels
+            .first()
+            .cloned()
+            .unwrap_or_else(|| candidates[0].clone());
+        Ok((label, Matching::Set { labels, all }))
+    }
+
+    fn parse_name(&mut self, what: &str) -> Result<(String, Range<usize>), QueryError> {
+        let token = self.next();
+        if let TokenKind::Name { text, .. } = &token.kind {
+            Ok((text.clone(), token.span.clone()))
+        } else {
+            Err(QueryError::new(
+                format!("expected {} but found {}", what, token.kind),
+                token.span.clone(),
+            ))
+        }
+    }
+}
+
+fn find_sub_level_label<'l, L, TL, SL>(candidates: &[&'l L], name: &str) -> Option<&'l L>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    candidates.iter().copied().find(|label| {
+        label
+            .get_sub_level_label()
+            .map(|sub_level_label| sub_level_label.to_string() == name)
+            .unwrap_or(false)
+    })
+}
+
+fn unknown_sub_level_label(name: &str, span: Range<usize>) -> QueryError {
+    QueryError::new(format!("unknown sub label '{}'", name), span)
+}
+
+/// prints the filters in the syntax of [`parse_query`]
+pub(crate) fn print_query<L, TL, SL>(root: &FilterGroup<SubFilterCore<L, TL, SL>>) -> String
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    match root.operator() {
+        GroupOperator::And | GroupOperator::Or => print_children(root),
+        GroupOperator::Not => print_node(&FilterNode::Group(root.clone())),
+    }
+}
+
+fn print_children<L, TL, SL>(group: &FilterGroup<SubFilterCore<L, TL, SL>>) -> String
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let separator = match group.operator() {
+        GroupOperator::And => " AND ",
+        GroupOperator::Or | GroupOperator::Not => " OR ",
+    };
+    group
+        .children()
+        .iter()
+        .map(print_node)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn print_node<L, TL, SL>(node: &FilterNode<SubFilterCore<L, TL, SL>>) -> String
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    match node {
+        FilterNode::Filter(filter) => print_filter(filter),
+        FilterNode::Group(group) => match (group.operator(), group.children()) {
+            //NOT (a AND b) does not need a second pair of parentheses
+            (GroupOperator::Not, [FilterNode::Group(child)])
+                if child.operator() == GroupOperator::And && child.children().len() > 1 =>
+            {
+                format!("NOT ({})", print_children(child))
+            }
+            (GroupOperator::Not, _) => format!("NOT ({})", print_children(group)),
+            _ => format!("({})", print_children(group)),
+        },
+    }
+}
+
+pub(crate) fn print_filter<L, TL, SL>(filter: &SubFilterCore<L, TL, SL>) -> String
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
+    let mut text = String::new();
+    if filter.inverted() {
+        text.push_str("NOT ");
+    }
+    if !filter.active() {
+        text.push('#');
+    }
+
+    let label = filter.label();
+    text.push_str(&quote(&label.get_top_level_label().to_string()));
+
+    let sub_level_name = |label: &L| {
+        label
+            .get_sub_level_label()
+            .map(|sub_level_label| quote(&sub_level_label.to_string()))
+            .unwrap_or_default()
+    };
+    match filter.matching() {
+        Matching::Exact => {
+            if label.get_sub_level_label().is_some() {
+                text.push(':');
+                text.push_str(&sub_level_name(label));
+            }
+        }
+        Matching::AnySubLabel => text.push_str(":*"),
+        Matching::Set { labels, all } => {
+            text.push(':');
+            if *all {
+                text.push_str("ALL");
+            }
+            text.push('{');
+            text.push_str(
+                &labels
+                    .iter()
+                    .map(sub_level_name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+            text.push('}');
+        }
+    }
+    text
+}
+
+/// quotes the name if it could not be parsed otherwise
+fn quote(name: &str) -> String {
+    let needs_quotes = name.is_empty()
+        || KEYWORDS.contains(&name)
+        || name
+            .chars()
+            .any(|c| c.is_whitespace() || SPECIAL_CHARACTERS.contains(&c) || c == '\\');
+
+    if needs_quotes {
+        let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{}\"", escaped)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+    struct TestLabel(&'static str, Option<&'static str>);
+
+    impl Label<&'static str, &'static str> for TestLabel {
+        fn get_top_level_label(&self) -> &&'static str {
+            &self.0
+        }
+
+        fn get_sub_level_label(&self) -> Option<&&'static str> {
+            self.1.as_ref()
+        }
+    }
+
+    type Filters = FilterGroup<SubFilterCore<TestLabel, &'static str, &'static str>>;
+
+    const LABELS: &[TestLabel] = &[
+        TestLabel("Weather", Some("Rain")),
+        TestLabel("Weather", Some("Snow")),
+        TestLabel("Weather", Some("Heavy Rain")),
+        TestLabel("Road", Some("Highway")),
+        TestLabel("Road", Some("City")),
+        TestLabel("Daytime", Some("Night")),
+        TestLabel("Tunnel", None),
+    ];
+
+    fn parse(query: &str) -> Result<Filters, QueryError> {
+        parse_query(query, LABELS)
+    }
+
+    /// the structure of the tree without the ids
+    fn structure(
+        node: &FilterNode<SubFilterCore<TestLabel, &'static str, &'static str>>,
+    ) -> String {
+        match node {
+            FilterNode::Filter(filter) => format!(
+                "{:?} {:?} {} {}",
+                filter.label(),
+                filter.matching(),
+                filter.inverted(),
+                filter.active()
+            ),
+            FilterNode::Group(group) => format!(
+                "{:?}[{}]",
+                group.operator(),
+                group
+                    .children()
+                    .iter()
+                    .map(structure)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// if data with the labels passes the filters
+    fn keeps(filters: &Filters, labels: &[&TestLabel]) -> bool {
+        filters
+            .evaluate(&mut |filter| {
+                if !filter.active() {
+                    return None;
+                }
+                let has = |label: &TestLabel| labels.contains(&label);
+                let matches = match filter.matching() {
+                    Matching::Exact => has(filter.label()),
+                    Matching::AnySubLabel => labels
+                        .iter()
+                        .any(|l| l.get_top_level_label() == filter.label().get_top_level_label()),
+                    Matching::Set { labels, all: false } => labels.iter().any(has),
+                    Matching::Set { labels, all: true } => labels.iter().all(has),
+                };
+                Some(matches != filter.inverted())
+            })
+            .unwrap_or(true)
+    }
+
+    /// checks that the printed query means the same for every combination of labels
+    fn assert_same_meaning(filters: &Filters) {
+        let reparsed = parse(&print_query(filters)).unwrap();
+        for combination in 0..1u32 << LABELS.len() {
+            let labels: Vec<&TestLabel> = LABELS
+                .iter()
+                .enumerate()
+                .filter(|(i, _label)| combination & 1 << i != 0)
+                .map(|(_i, label)| label)
+                .collect();
+            assert_eq!(
+                keeps(filters, &labels),
+                keeps(&reparsed, &labels),
+                "{} with {:?}",
+                print_query(filters),
+                labels
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let queries = [
+            "",
+            "Weather:Rain",
+            "Tunnel",
+            "Weather:Rain AND NOT Road:Highway",
+            "(Weather:{Rain, Snow} OR Daytime:Night) AND Road:*",
+            "Weather:ALL{Rain, Snow} OR #Tunnel",
+            "NOT #Weather:\"Heavy Rain\" OR (Road:City AND Tunnel)",
+            "NOT (Weather:Rain OR Road:Highway) AND Tunnel",
+            "NOT (Weather:Rain AND Road:Highway)",
+            "NOT (Weather:Rain)",
+            "(Weather:Rain OR Road:City) OR (Daytime:Night AND Tunnel)",
+            "Weather:{} AND ()",
+        ];
+        for query in queries {
+            let filters = parse(query).unwrap();
+            let printed = print_query(&filters);
+            assert_eq!(printed, query);
+
+            let reparsed = parse(&printed).unwrap();
+            assert_eq!(
+                structure(&FilterNode::Group(reparsed)),
+                structure(&FilterNode::Group(filters.clone())),
+                "{}",
+                query
+            );
+            assert_same_meaning(&filters);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_meaning() {
+        let filter = |id: u32, label: usize, inverted: bool| {
+            FilterNode::Filter(SubFilterCore::new(
+                id,
+                LABELS[label].clone(),
+                Matching::Exact,
+                inverted,
+                true,
+            ))
+        };
+
+        //a not group as root
+        let mut root = FilterGroup::new(0, GroupOperator::Not);
+        root.push(filter(1, 0, false));
+        root.push(filter(2, 3, true));
+        assert_same_meaning(&root);
+
+        //groups with one child
+        let mut root = FilterGroup::new(0, GroupOperator::Or);
+        let mut or = FilterGroup::new(1, GroupOperator::Or);
+        or.push(filter(2, 5, false));
+        root.push(FilterNode::Group(or));
+        let mut not = FilterGroup::new(3, GroupOperator::Not);
+        let mut and = FilterGroup::new(4, GroupOperator::And);
+        and.push(filter(5, 6, true));
+        not.push(FilterNode::Group(and));
+        root.push(FilterNode::Group(not));
+        assert_same_meaning(&root);
+    }
+
+    #[test]
+    fn error_spans() {
+        let cases = [
+            ("Wether:Rain", 0..6),
+            ("Weather:Rian", 8..12),
+            ("Weather", 0..7),
+            ("Weather:{Rain, Sun}", 15..18),
+            ("Weather:Rain AND", 16..16),
+            ("(Weather:Rain", 13..13),
+            ("Weather:Rain)", 12..13),
+            ("Tunnel AND \"Heavy", 11..17),
+            ("Tunnel Road:City", 7..11),
+        ];
+        for (query, span) in cases {
+            let error = parse(query).err().unwrap();
+            assert_eq!(error.span(), span, "{}: {}", query, error);
+        }
+    }
+}
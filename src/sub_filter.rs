@@ -1,12 +1,36 @@
+#[cfg(feature = "egui")]
+use std::fmt::Display;
 use std::{marker::PhantomData, ops::Deref};
 
+#[cfg(feature = "egui")]
 use eframe::{
-    egui::{ComboBox, Label as EguiLabel, RichText, Ui},
+    egui::{Label as EguiLabel, RichText, Ui},
     epaint::Color32,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "egui")]
+use crate::label_search::{popup_button, show_searchable_options, SearchOption};
 use crate::{Label, LabeledData, SubLabel, TopLabel};
 
+/// which labels of the data are matched by a filter
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) enum Matching<L> {
+    /// just the exact label of the filter
+    Exact,
+    /// every label with the same top level label as the label of the filter
+    AnySubLabel,
+    /// the labels of the set, the data needs to have one of them or if all is set all of them
+    Set { labels: Vec<L>, all: bool },
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(serialize = "L: Serialize", deserialize = "L: Deserialize<'de>"))
+)]
 pub(crate) struct SubFilterCore<L, TL, SL>
 where
     L: Label<TL, SL>,
@@ -15,10 +39,13 @@ where
 {
     id: u32,
     label: L,
+    matching: Matching<L>,
     inverted: bool,
     active: bool,
 
+    #[cfg_attr(feature = "serde", serde(skip))]
     marker_0: PhantomData<TL>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     marker_1: PhantomData<SL>,
 }
 
@@ -28,10 +55,17 @@ where
     TL: TopLabel,
     SL: SubLabel,
 {
-    fn new(id: u32, label: L, inverted: bool, active: bool) -> Self {
+    pub(crate) fn new(
+        id: u32,
+        label: L,
+        matching: Matching<L>,
+        inverted: bool,
+        active: bool,
+    ) -> Self {
         Self {
             id,
             label,
+            matching,
             inverted,
             active,
             marker_0: PhantomData,
@@ -39,17 +73,55 @@ where
         }
     }
 
-    pub(crate) fn filter<D: LabeledData<L, TL, SL>>(&self, data: &D) -> bool {
-        !self.active || data.get_labels().contains(&self.label) ^ self.inverted
+    /// returns None if the filter is not active
+    pub(crate) fn filter<D: LabeledData<L, TL, SL>>(&self, data: &D) -> Option<bool> {
+        self.active.then(|| {
+            let labels = data.get_labels();
+            let matches = match &self.matching {
+                Matching::Exact => labels.contains(&self.label),
+                Matching::Set { labels: set, all } => {
+                    if *all {
+                        set.iter().all(|label| labels.contains(label))
+                    } else {
+                        set.iter().any(|label| labels.contains(label))
+                    }
+                }
+                Matching::AnySubLabel => labels
+                    .iter()
+                    .any(|label| label.get_top_level_label() == self.label.get_top_level_label()),
+            };
+            matches ^ self.inverted
+        })
     }
 
     pub(crate) fn label(&self) -> &L {
         &self.label
     }
 
+    pub(crate) fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    pub(crate) fn active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn matching(&self) -> &Matching<L> {
+        &self.matching
+    }
+
     pub(crate) fn id(&self) -> u32 {
         self.id
     }
+
+    /// returns if both filters are configured the same, unlike == which just compares the ids
+    pub(crate) fn same_config(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.label == other.label
+            && self.matching == other.matching
+            && self.inverted == other.inverted
+            && self.active == other.active
+    }
 }
 
 impl<L, TL, SL> Clone for SubFilterCore<L, TL, SL>
@@ -62,6 +134,7 @@ where
         Self {
             id: self.id,
             label: self.label.clone(),
+            matching: self.matching.clone(),
             inverted: self.inverted,
             active: self.active,
             marker_0: PhantomData,
@@ -79,8 +152,12 @@ where
     fn eq(&self, other: &Self) -> bool {
         let eq = self.id == other.id;
 
-        if eq && (self.label != other.label || self.inverted != other.inverted) {
-            panic!("FilterCore has same Id but not same label and same inversion")
+        if eq
+            && (self.label != other.label
+                || self.matching != other.matching
+                || self.inverted != other.inverted)
+        {
+            panic!("FilterCore has same Id but not same label, matching and inversion")
         }
         eq
     }
@@ -94,6 +171,55 @@ where
 {
 }
 
+/// the options of a filter together with the number of data which is left if they are selected
+/// options are usefull if selecting them changes which data is filtered out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelOptions<L> {
+    pub usefull_top_level_labels: Vec<(L, u32)>,
+    pub useless_top_level_labels: Vec<(L, u32)>,
+    pub usefull_sub_level_labels: Vec<(L, u32)>,
+    pub useless_sub_level_labels: Vec<(L, u32)>,
+    pub any_sub_level_label_usefull: bool,
+    pub any_sub_level_label_count: u32,
+    /// the number of data which passes the other filters, as if the filter was not active
+    pub passing_other_filters: u32,
+    /// the number of data which passes all filters
+    pub passing_all_filters: u32,
+}
+
+impl<L> LabelOptions<L> {
+    /// the number of data which is filtered out by the filter,
+    /// negative if the filter lets more data pass, e.g. in an or group or a not group
+    pub fn removed(&self) -> i64 {
+        self.passing_other_filters as i64 - self.passing_all_filters as i64
+    }
+}
+
+impl<L> Default for LabelOptions<L> {
+    fn default() -> Self {
+        Self {
+            usefull_top_level_labels: Vec::new(),
+            useless_top_level_labels: Vec::new(),
+            usefull_sub_level_labels: Vec::new(),
+            useless_sub_level_labels: Vec::new(),
+            any_sub_level_label_usefull: false,
+            any_sub_level_label_count: 0,
+            passing_other_filters: 0,
+            passing_all_filters: 0,
+        }
+    }
+}
+
+/// the text of the sub level option which matches every sub level label
+#[cfg(feature = "egui")]
+const ANY_SUB_LEVEL_LABEL: &str = "(any)";
+/// the text of the sub level option which matches a set of sub level labels
+#[cfg(feature = "egui")]
+const SET: &str = "(one of ...)";
+/// the text of a set filter without labels
+#[cfg(feature = "egui")]
+const EMPTY_SET: &str = "(none)";
+
 #[derive(Clone)]
 pub(crate) struct SubFilter<L, TL, SL>
 where
@@ -102,11 +228,7 @@ where
     SL: SubLabel,
 {
     core: SubFilterCore<L, TL, SL>,
-
-    usefull_top_level_labels: Vec<L>,
-    useless_top_level_labels: Vec<L>,
-    usefull_sub_level_labels: Vec<L>,
-    useless_sub_level_labels: Vec<L>,
+    options: LabelOptions<L>,
 }
 
 impl<L, TL, SL> SubFilter<L, TL, SL>
@@ -117,31 +239,34 @@ where
 {
     pub(crate) fn new(label: L, id: u32) -> SubFilter<L, TL, SL> {
         SubFilter {
-            core: SubFilterCore::new(id, label, false, true),
-            usefull_top_level_labels: Vec::new(),
-            useless_top_level_labels: Vec::new(),
-            usefull_sub_level_labels: Vec::new(),
-            useless_sub_level_labels: Vec::new(),
+            core: SubFilterCore::new(id, label, Matching::Exact, false, true),
+            options: LabelOptions::default(),
         }
     }
 
     pub(crate) fn from_core_with_label_options(
         core: SubFilterCore<L, TL, SL>,
-
-        usefull_top_level_labels: Vec<L>,
-        useless_top_level_labels: Vec<L>,
-        usefull_sub_level_labels: Vec<L>,
-        useless_sub_level_labels: Vec<L>,
+        options: LabelOptions<L>,
     ) -> SubFilter<L, TL, SL> {
-        SubFilter {
-            core,
-            usefull_top_level_labels,
-            useless_top_level_labels,
-            usefull_sub_level_labels,
-            useless_sub_level_labels,
-        }
+        SubFilter { core, options }
+    }
+
+    pub(crate) fn label_options(&self) -> &LabelOptions<L> {
+        &self.options
+    }
+
+    pub(crate) fn clone_core(&self) -> SubFilterCore<L, TL, SL> {
+        self.core.clone()
     }
+}
 
+#[cfg(feature = "egui")]
+impl<L, TL, SL> SubFilter<L, TL, SL>
+where
+    L: Label<TL, SL>,
+    TL: TopLabel,
+    SL: SubLabel,
+{
     pub(crate) fn show(&mut self, ui: &mut Ui) -> FilterInfo {
         let inner_changed = ui
             .add_enabled_ui(self.core.active, |ui| {
@@ -154,82 +279,52 @@ where
                     ui.add_sized(size, EguiLabel::new(""));
                 }
 
-                let top_changed = ComboBox::from_id_source(format!("top_level_label_{}", self.id))
-                    .selected_text(self.label.get_top_level_label().to_string())
-                    .show_ui(ui, |ui| {
-                        let mut changed = false;
-                        for top_level_label in self.usefull_top_level_labels.iter() {
-                            changed |= ui
-                                .selectable_value(
-                                    &mut self.core.label,
-                                    top_level_label.clone(),
-                                    top_level_label.get_top_level_label().to_string(),
-                                )
-                                .changed();
-                        }
-                        for top_level_label in self.useless_top_level_labels.iter() {
-                            let text =
-                                RichText::new(top_level_label.get_top_level_label().to_string())
-                                    .color(Color32::DARK_GRAY);
-                            changed |= ui
-                                .selectable_value(
-                                    &mut self.core.label,
-                                    top_level_label.clone(),
-                                    text,
-                                )
-                                .changed();
-                        }
-                        changed
-                    })
-                    .inner
-                    .unwrap_or(false);
-
-                let sub_changed = if let Some(sub_level_label) = self.label.get_sub_level_label() {
-                    ComboBox::from_id_source(format!("sub_level_label_{}", self.id))
-                        .selected_text(sub_level_label.to_string())
-                        .show_ui(ui, |ui| {
-                            let mut changed = false;
-                            for sub_level_label in self.usefull_sub_level_labels.iter() {
-                                let text = if let Some(sub_level_label) =
-                                    sub_level_label.get_sub_level_label()
-                                {
-                                    sub_level_label.to_string()
-                                } else {
-                                    continue;
-                                };
-                                changed |= ui
-                                    .selectable_value(
-                                        &mut self.core.label,
-                                        sub_level_label.clone(),
-                                        text,
-                                    )
-                                    .changed();
-                            }
-                            for sub_level_label in self.useless_sub_level_labels.iter() {
-                                let text = if let Some(sub_level_label) =
-                                    sub_level_label.get_sub_level_label()
-                                {
-                                    RichText::new(sub_level_label.to_string())
-                                        .color(Color32::DARK_GRAY)
-                                } else {
-                                    continue;
-                                };
-                                changed |= ui
-                                    .selectable_value(
-                                        &mut self.core.label,
-                                        sub_level_label.clone(),
-                                        text,
-                                    )
-                                    .changed();
-                            }
-                            changed
+                let id = ui.make_persistent_id(format!("top_level_label_{}", self.id));
+                let selected_text = self.label.get_top_level_label().to_string();
+                let top_changed = popup_button(ui, id, &selected_text, |ui| {
+                    let usefull = self
+                        .options
+                        .usefull_top_level_labels
+                        .iter()
+                        .map(|l| (l, true));
+                    let useless = self
+                        .options
+                        .useless_top_level_labels
+                        .iter()
+                        .map(|l| (l, false));
+                    let top_level_labels: Vec<_> = usefull.chain(useless).collect();
+                    let options: Vec<_> = top_level_labels
+                        .iter()
+                        .map(|((top_level_label, count), usefull)| SearchOption {
+                            name: top_level_label.get_top_level_label().to_string(),
+                            count: *count,
+                            usefull: *usefull,
+                            selected: &self.core.label == top_level_label,
                         })
-                        .inner
-                        .unwrap_or(false)
-                } else {
-                    ui.label("");
-                    false
-                };
+                        .collect();
+
+                    let id = ui.make_persistent_id(format!("top_level_search_{}", self.id));
+                    let chosen = show_searchable_options(ui, id, &options, false);
+                    if chosen.is_some() {
+                        ui.memory().close_popup();
+                    }
+                    match chosen {
+                        Some(i) if !options[i].selected => {
+                            let ((top_level_label, _count), _usefull) = top_level_labels[i];
+                            self.core.label = top_level_label.clone();
+                            true
+                        }
+                        _ => false,
+                    }
+                })
+                .unwrap_or(false);
+
+                if top_changed {
+                    //the count of a top level option is the number of data with any of its labels
+                    self.core.matching = Matching::AnySubLabel;
+                }
+
+                let sub_changed = self.show_sub_level_options(ui);
 
                 let inverted = if ui.button("invert").clicked() {
                     self.core.inverted = !self.core.inverted;
@@ -246,14 +341,152 @@ where
 
         let activision_changed = ui.checkbox(&mut self.core.active, "").changed();
 
+        //shows which filter is responsible for an empty result
+        //a filter in an or group or a not group can let more items pass
+        let removed_items = self.options.removed();
+        let (sign, color) = if !self.core.active || removed_items == 0 {
+            ("-", Color32::DARK_GRAY)
+        } else if removed_items > 0 {
+            ("-", Color32::LIGHT_RED)
+        } else {
+            ("+", Color32::LIGHT_GREEN)
+        };
+        let text = format!(
+            "{}{} items",
+            sign,
+            group_digits(removed_items.unsigned_abs() as u32)
+        );
+        ui.label(RichText::new(text).color(color))
+            .on_hover_text(format!(
+                "of the {} items which pass the other filters {} pass this filter",
+                group_digits(self.options.passing_other_filters),
+                group_digits(self.options.passing_all_filters),
+            ));
+
         FilterInfo {
             was_changed: inner_changed || activision_changed || removed,
             needs_removal: removed,
         }
     }
 
-    pub(crate) fn clone_core(&self) -> SubFilterCore<L, TL, SL> {
-        self.core.clone()
+    /// shows the popup of the sub level labels
+    /// for a set filter every label gets a checkbox instead of a selectable label and the popup stays open
+    fn show_sub_level_options(&mut self, ui: &mut Ui) -> bool {
+        let sub_level_label = if let Some(sub_level_label) = self.label.get_sub_level_label() {
+            sub_level_label
+        } else {
+            ui.label("");
+            return false;
+        };
+
+        let selected_text = match &self.matching {
+            Matching::Exact => sub_level_label.to_string(),
+            Matching::AnySubLabel => ANY_SUB_LEVEL_LABEL.to_string(),
+            Matching::Set { labels, .. } if labels.is_empty() => EMPTY_SET.to_string(),
+            Matching::Set { labels, all } => {
+                let separator = if *all { " & " } else { " | " };
+                labels
+                    .iter()
+                    .filter_map(|label| label.get_sub_level_label())
+                    .map(|label| label.to_string())
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            }
+        };
+
+        let id = ui.make_persistent_id(format!("sub_level_label_{}", self.id));
+        popup_button(ui, id, &selected_text, |ui| {
+            let mut changed = false;
+
+            let any_selected = self.matching == Matching::AnySubLabel;
+            let text = RichText::new(text_with_count(
+                ANY_SUB_LEVEL_LABEL,
+                self.options.any_sub_level_label_count,
+            ));
+            let text = if self.options.any_sub_level_label_usefull {
+                text
+            } else {
+                text.color(Color32::DARK_GRAY)
+            };
+            if ui.selectable_label(any_selected, text).clicked() {
+                ui.memory().close_popup();
+                if !any_selected {
+                    self.core.matching = Matching::AnySubLabel;
+                    changed = true;
+                }
+            }
+
+            let set_selected = matches!(self.matching, Matching::Set { .. });
+            if ui.selectable_label(set_selected, SET).clicked() && !set_selected {
+                self.core.matching = Matching::Set {
+                    labels: vec![self.core.label.clone()],
+                    all: false,
+                };
+                changed = true;
+            }
+
+            if let Matching::Set { all, .. } = &mut self.core.matching {
+                changed |= ui.checkbox(all, "all of").changed();
+            }
+            ui.separator();
+
+            let usefull = self
+                .options
+                .usefull_sub_level_labels
+                .iter()
+                .map(|l| (l, true));
+            let useless = self
+                .options
+                .useless_sub_level_labels
+                .iter()
+                .map(|l| (l, false));
+            let sub_level_labels: Vec<_> = usefull
+                .chain(useless)
+                .filter(|((label, _count), _usefull)| label.get_sub_level_label().is_some())
+                .collect();
+            let options: Vec<_> = sub_level_labels
+                .iter()
+                .map(|((label, count), usefull)| SearchOption {
+                    name: label
+                        .get_sub_level_label()
+                        .map(|sub_level_label| sub_level_label.to_string())
+                        .unwrap_or_default(),
+                    count: *count,
+                    usefull: *usefull,
+                    selected: match &self.core.matching {
+                        Matching::Set { labels, .. } => labels.contains(label),
+                        matching => *matching == Matching::Exact && &self.core.label == label,
+                    },
+                })
+                .collect();
+
+            let is_set = matches!(self.core.matching, Matching::Set { .. });
+            let id = ui.make_persistent_id(format!("sub_level_search_{}", self.id));
+            if let Some(i) = show_searchable_options(ui, id, &options, is_set) {
+                let ((sub_level_label, _count), _usefull) = sub_level_labels[i];
+                match &mut self.core.matching {
+                    Matching::Set { labels, .. } => {
+                        if options[i].selected {
+                            labels.retain(|label| label != sub_level_label);
+                        } else {
+                            labels.push(sub_level_label.clone());
+                            labels.sort();
+                        }
+                        changed = true;
+                    }
+                    matching => {
+                        ui.memory().close_popup();
+                        if !options[i].selected {
+                            self.core.label = sub_level_label.clone();
+                            *matching = Matching::Exact;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            changed
+        })
+        .unwrap_or(false)
     }
 }
 
@@ -279,15 +512,36 @@ where
     fn from(core: SubFilterCore<L, TL, SL>) -> Self {
         SubFilter {
             core,
-
-            usefull_top_level_labels: Vec::new(),
-            useless_top_level_labels: Vec::new(),
-            usefull_sub_level_labels: Vec::new(),
-            useless_sub_level_labels: Vec::new(),
+            options: LabelOptions::default(),
         }
     }
 }
 
+/// formats an option together with the number of data which is left if it is selected
+/// e.g. "Rain (1 234)"
+#[cfg(feature = "egui")]
+pub(crate) fn text_with_count(text: impl Display, count: u32) -> String {
+    format!("{} ({})", text, group_digits(count))
+}
+
+/// e.g. "1 234"
+#[cfg(feature = "egui")]
+pub(crate) fn group_digits(count: u32) -> String {
+    let digits = count.to_string();
+    let (head, tail) = digits.split_at(digits.len() % 3);
+    Some(head)
+        .filter(|head| !head.is_empty())
+        .into_iter()
+        .chain(
+            tail.as_bytes()
+                .chunks(3)
+                .map(|group| std::str::from_utf8(group).expect("digits to be ascii")),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(feature = "egui")]
 pub(crate) struct FilterInfo {
     pub(crate) needs_removal: bool,
     pub(crate) was_changed: bool,